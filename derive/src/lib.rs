@@ -0,0 +1,229 @@
+//! `#[derive(FromSdl, ToSdl)]` for `rust_sdl`. See the `rust_sdl::convert`
+//! module for the field attributes.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr};
+
+#[proc_macro_derive(FromSdl, attributes(sdl))]
+pub fn derive_from_sdl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, from_sdl)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(ToSdl, attributes(sdl))]
+pub fn derive_to_sdl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, to_sdl)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// Where a field is read from and written to.
+enum Kind {
+    Value,
+    Attribute(String),
+    Child(String),
+    Children(String),
+    Rest,
+}
+
+struct Field {
+    ident: Ident,
+    kind: Kind,
+}
+
+fn expand(
+    input: &DeriveInput,
+    body: fn(&DeriveInput, &[Field]) -> TokenStream2,
+) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "SDL conversions need a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "SDL conversions can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut parsed = Vec::new();
+    for field in fields {
+        let ident = field.ident.clone().expect("named field");
+        parsed.push(Field {
+            kind: kind(field, &ident)?,
+            ident,
+        });
+    }
+
+    if parsed
+        .iter()
+        .filter(|f| matches!(f.kind, Kind::Rest))
+        .count()
+        > 1
+    {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "only one field can be #[sdl(rest)]",
+        ));
+    }
+
+    Ok(body(input, &parsed))
+}
+
+fn kind(field: &syn::Field, ident: &Ident) -> syn::Result<Kind> {
+    let default_name = ident.unraw().to_string();
+    let mut kind = None;
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("sdl")) {
+        attr.parse_nested_meta(|meta| {
+            let name = || -> syn::Result<String> {
+                if meta.input.peek(syn::Token![=]) {
+                    Ok(meta.value()?.parse::<LitStr>()?.value())
+                } else {
+                    Ok(default_name.clone())
+                }
+            };
+
+            let parsed = if meta.path.is_ident("value") {
+                Kind::Value
+            } else if meta.path.is_ident("attr") {
+                Kind::Attribute(name()?)
+            } else if meta.path.is_ident("child") {
+                Kind::Child(name()?)
+            } else if meta.path.is_ident("children") {
+                Kind::Children(name()?)
+            } else if meta.path.is_ident("rest") {
+                Kind::Rest
+            } else {
+                return Err(meta.error("expected `value`, `attr`, `child`, `children` or `rest`"));
+            };
+
+            if kind.replace(parsed).is_some() {
+                return Err(meta.error("a field can only have one #[sdl] kind"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(kind.unwrap_or(Kind::Child(default_name)))
+}
+
+fn from_sdl(input: &DeriveInput, fields: &[Field]) -> TokenStream2 {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let local = |field: &Field| format_ident!("__{}", field.ident.unraw());
+
+    let reads = fields.iter().map(|field| {
+        let local = local(field);
+        let name = field.ident.unraw().to_string();
+
+        match &field.kind {
+            Kind::Value => quote! {
+                let #local = ::rust_sdl::convert::read_value(values.next(), #name, tag.line())?;
+            },
+            Kind::Attribute(attr) => quote! {
+                let #local = ::rust_sdl::convert::read_attribute(&mut tag, #attr)?;
+            },
+            Kind::Child(child) => quote! {
+                let #local = ::rust_sdl::convert::read_child(&mut tag, #child)?;
+            },
+            Kind::Children(child) => quote! {
+                let #local = ::rust_sdl::convert::read_children(&mut tag, #child)?;
+            },
+            Kind::Rest => quote! {},
+        }
+    });
+
+    // The rest is whatever is left once every other field has been read.
+    let rest = fields
+        .iter()
+        .filter(|field| matches!(field.kind, Kind::Rest))
+        .map(|field| {
+            let local = local(field);
+            quote! { let #local = tag; }
+        });
+
+    let inits = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let local = local(field);
+        quote! { #ident: #local }
+    });
+
+    quote! {
+        impl #impl_generics ::rust_sdl::convert::FromSdl for #ident #ty_generics #where_clause {
+            fn from_tag(
+                tag: ::rust_sdl::parser::Tag,
+            ) -> ::std::result::Result<Self, ::rust_sdl::convert::ConvertError> {
+                #[allow(unused_mut)]
+                let mut tag = tag;
+                #[allow(unused_mut)]
+                let mut values = ::std::mem::take(tag.values_mut()).into_iter();
+                #(#reads)*
+                tag.values_mut().extend(values);
+                #(#rest)*
+                ::std::result::Result::Ok(#ident { #(#inits),* })
+            }
+        }
+    }
+}
+
+fn to_sdl(input: &DeriveInput, fields: &[Field]) -> TokenStream2 {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let writes = fields.iter().map(|field| {
+        let ident = &field.ident;
+
+        match &field.kind {
+            Kind::Value => quote! {
+                ::rust_sdl::convert::write_value(&mut tag, &self.#ident);
+            },
+            Kind::Attribute(attr) => quote! {
+                ::rust_sdl::convert::write_attribute(&mut tag, #attr, &self.#ident);
+            },
+            Kind::Child(child) => quote! {
+                ::rust_sdl::convert::write_child(&mut tag, #child, &self.#ident);
+            },
+            Kind::Children(child) => quote! {
+                for item in &self.#ident {
+                    tag.add_child(::rust_sdl::convert::ToSdl::to_tag(item, #child));
+                }
+            },
+            Kind::Rest => quote! {},
+        }
+    });
+
+    // Written last so that its values follow those of the other fields.
+    let rest = fields
+        .iter()
+        .filter(|field| matches!(field.kind, Kind::Rest))
+        .map(|field| {
+            let ident = &field.ident;
+            quote! { ::rust_sdl::convert::write_rest(&mut tag, &self.#ident); }
+        });
+
+    quote! {
+        impl #impl_generics ::rust_sdl::convert::ToSdl for #ident #ty_generics #where_clause {
+            fn to_tag(&self, name: &str) -> ::rust_sdl::parser::Tag {
+                #[allow(unused_mut)]
+                let mut tag = ::rust_sdl::parser::Tag::new(::std::string::String::from(name));
+                #(#writes)*
+                #(#rest)*
+                tag
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+// Run with `cargo fuzz run scan ../tests/corpus` from this directory.
+// Reproducers for failures belong in `tests/corpus`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_sdl::parser::Parser;
+use rust_sdl::scanner::{Scanner, Token};
+use std::io::BufReader;
+
+fuzz_target!(|data: &[u8]| {
+    let source = match std::str::from_utf8(data) {
+        Ok(source) => source,
+        Err(_) => {
+            // Invalid input must end with an error, not a panic.
+            let _ = Scanner::from_reader(data).count();
+            return;
+        }
+    };
+
+    // Every token but the last consumes at least one character.
+    let bound = source.chars().count() + 1;
+    let mut scanner = Scanner::new(source);
+    let tokens: Vec<Token> = scanner.by_ref().take(bound).collect();
+    assert_eq!(scanner.next(), None);
+
+    let mut previous_end = 0;
+    for token in &tokens {
+        let (start, end, _) = token.position();
+        assert!(previous_end <= start && start <= end && end <= source.len());
+        previous_end = end;
+    }
+
+    let streamed: Vec<Token> = Scanner::from_reader(BufReader::with_capacity(3, data))
+        .take(bound)
+        .collect();
+    assert_eq!(streamed, tokens);
+
+    let _ = Parser::from_str(source).parse();
+});
@@ -0,0 +1,222 @@
+use crate::parser::{Tag, Value};
+
+/// Builds a `Tag` one call at a time.
+///
+/// ```
+/// use rust_sdl::parser::Tag;
+///
+/// let format = Tag::build("format")
+///     .value("prettier")
+///     .attr("prettier", true)
+///     .child(Tag::build("width").value(80))
+///     .build();
+///
+/// assert_eq!(format.attribute("prettier").map(|v| v.to_string()), Some("true".into()));
+/// ```
+pub struct TagBuilder {
+    tag: Tag,
+}
+
+impl Tag {
+    pub fn build(name: impl Into<String>) -> TagBuilder {
+        TagBuilder {
+            tag: Tag::new(name.into()),
+        }
+    }
+}
+
+impl TagBuilder {
+    pub fn value(mut self, value: impl Into<Value>) -> Self {
+        self.tag.push_value(value.into());
+        self
+    }
+
+    /// Sets an attribute. A repeated name replaces the earlier value.
+    pub fn attr(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.tag.set_attribute(name, value.into());
+        self
+    }
+
+    pub fn child(mut self, child: impl Into<Tag>) -> Self {
+        self.tag.add_child(child.into());
+        self
+    }
+
+    pub fn build(self) -> Tag {
+        self.tag
+    }
+}
+
+impl From<TagBuilder> for Tag {
+    fn from(builder: TagBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// Builds a `Vec<Tag>` from SDL written inline.
+///
+/// The grammar is the one `Parser` accepts, with two differences forced by
+/// Rust's tokenizer: tag and attribute names that are not Rust identifiers,
+/// like `dev-deps` or `lint:fix`, are written as string literals, and the
+/// last tag of a body may leave out its `;`. Integer literals are
+/// `Value::Integer` unless given an `i64` suffix. Every token of the
+/// document is a step of macro recursion, so large documents may need a
+/// higher `#![recursion_limit]`.
+///
+/// ```
+/// use rust_sdl::sdl;
+///
+/// let tags = sdl! {
+///     author "Kirill";
+///     scripts {
+///         build { prod "microbundle" }
+///         "lint:fix" "eslint" "no-cache"=true;
+///     }
+///     format "prettier" prettier=true;
+/// };
+///
+/// assert_eq!(tags.len(), 3);
+/// assert_eq!(tags[1].child("build").unwrap().children()[0].name(), "prod");
+/// assert!(tags[1].child("lint:fix").unwrap().attribute("no-cache").is_some());
+/// ```
+#[macro_export]
+macro_rules! sdl {
+    (@tags $tags:ident;) => {};
+    (@tags $tags:ident; $name:ident $($rest:tt)*) => {
+        #[allow(unused_mut)]
+        let mut tag = $crate::parser::Tag::new(::std::string::String::from(stringify!($name)));
+        $crate::sdl!(@contents $tags tag; $($rest)*);
+    };
+    (@tags $tags:ident; $name:literal $($rest:tt)*) => {
+        #[allow(unused_mut)]
+        let mut tag = $crate::parser::Tag::new(::std::string::String::from($name));
+        $crate::sdl!(@contents $tags tag; $($rest)*);
+    };
+
+    (@contents $tags:ident $tag:ident;) => {
+        $tags.push($tag);
+    };
+    (@contents $tags:ident $tag:ident; ; $($rest:tt)*) => {
+        $tags.push($tag);
+        $crate::sdl!(@tags $tags; $($rest)*);
+    };
+    (@contents $tags:ident $tag:ident; { $($body:tt)* } $($rest:tt)*) => {
+        for child in $crate::sdl!($($body)*) {
+            $tag.add_child(child);
+        }
+        $tags.push($tag);
+        $crate::sdl!(@tags $tags; $($rest)*);
+    };
+    (@contents $tags:ident $tag:ident; null $($rest:tt)*) => {
+        $tag.push_value($crate::parser::Value::Null);
+        $crate::sdl!(@contents $tags $tag; $($rest)*);
+    };
+    (@contents $tags:ident $tag:ident; $name:ident = null $($rest:tt)*) => {
+        $tag.set_attribute(stringify!($name), $crate::parser::Value::Null);
+        $crate::sdl!(@contents $tags $tag; $($rest)*);
+    };
+    (@contents $tags:ident $tag:ident; $name:ident = $value:literal $($rest:tt)*) => {
+        $tag.set_attribute(stringify!($name), $crate::parser::Value::from($value));
+        $crate::sdl!(@contents $tags $tag; $($rest)*);
+    };
+    (@contents $tags:ident $tag:ident; $name:literal = null $($rest:tt)*) => {
+        $tag.set_attribute($name, $crate::parser::Value::Null);
+        $crate::sdl!(@contents $tags $tag; $($rest)*);
+    };
+    (@contents $tags:ident $tag:ident; $name:literal = $value:literal $($rest:tt)*) => {
+        $tag.set_attribute($name, $crate::parser::Value::from($value));
+        $crate::sdl!(@contents $tags $tag; $($rest)*);
+    };
+    (@contents $tags:ident $tag:ident; $value:literal $($rest:tt)*) => {
+        $tag.push_value($crate::parser::Value::from($value));
+        $crate::sdl!(@contents $tags $tag; $($rest)*);
+    };
+
+    ($($document:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut tags: ::std::vec::Vec<$crate::parser::Tag> = ::std::vec::Vec::new();
+        $crate::sdl!(@tags tags; $($document)*);
+        tags
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::{IntegerOverflow, ParserOptions};
+    use crate::parser::{Parser, Tag};
+    use crate::scanner::Scanner;
+    use crate::writer::write;
+
+    #[test]
+    fn builder() {
+        let tag = Tag::build("scripts")
+            .child(Tag::build("build").attr("prod", "microbundle"))
+            .attr("prettier", true)
+            .value("run")
+            .value(2)
+            .build();
+
+        assert_eq!(
+            write(&[tag]).unwrap(),
+            "scripts \"run\" 2 prettier=true {\n  build prod=\"microbundle\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn macro_matches_parser() {
+        let source = r#"
+            author "Kirill";
+            enabled;
+            scripts {
+                build { prod "microbundle"; dev "microbundle watch"; }
+                lint "eslint" fix=true;
+            }
+            numbers 1 3000000000 1.5 true false null timeout=null;
+        "#;
+        let options = ParserOptions {
+            integer_overflow: IntegerOverflow::Widen,
+            ..ParserOptions::default()
+        };
+        let parsed = Parser::with_options(Scanner::new(source), options)
+            .parse()
+            .unwrap();
+        let built = sdl! {
+            author "Kirill";
+            enabled;
+            scripts {
+                build { prod "microbundle"; dev "microbundle watch" }
+                lint "eslint" fix=true
+            }
+            numbers 1 3000000000i64 1.5 true false null timeout=null;
+        };
+
+        assert_eq!(write(&built).unwrap(), write(&parsed).unwrap());
+    }
+
+    #[test]
+    fn string_names() {
+        let parsed = Parser::from_str(
+            "dev-deps 1; tools { lint:fix \"eslint\" no-cache=true timeout=null; }",
+        )
+        .parse()
+        .unwrap();
+        let built = sdl! {
+            "dev-deps" 1;
+            tools {
+                "lint:fix" "eslint" "no-cache"=true "timeout"=null
+            }
+        };
+
+        assert_eq!(built, parsed);
+        assert_eq!(built[0].name(), "dev-deps");
+        assert_eq!(
+            built[1].children()[0].attribute("no-cache"),
+            Some(&true.into())
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert!(sdl! {}.is_empty());
+    }
+}
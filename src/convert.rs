@@ -0,0 +1,578 @@
+//! Conversions between `Tag`s and Rust types.
+//!
+//! `#[derive(FromSdl, ToSdl)]` implements the traits below for structs with
+//! named fields. Each field is read from one part of the tag, chosen with an
+//! `sdl` attribute:
+//!
+//! - `#[sdl(value)]`: the next value of the tag, in field order.
+//! - `#[sdl(attr)]` or `#[sdl(attr = "name")]`: an attribute.
+//! - `#[sdl(child)]` or `#[sdl(child = "name")]`: the first child with that
+//!   name. This is the default for fields without an `sdl` attribute.
+//! - `#[sdl(children)]` or `#[sdl(children = "name")]`: every child with
+//!   that name, collected into a `Vec` or other collection.
+//! - `#[sdl(rest)]`: a `Tag` with the values, attributes and children not
+//!   read by other fields.
+//!
+//! Names default to the name of the field. Fields of type `Option` may be
+//! missing, and numbers widen as with `Numeric::Lossless`. A child read into
+//! a plain type such as `i32` or `String` must have a single value, as in
+//! `port 8080;`.
+//!
+//! ```
+//! use rust_sdl::{convert, sdl, FromSdl, ToSdl};
+//!
+//! #[derive(FromSdl, ToSdl)]
+//! struct Server {
+//!     #[sdl(value)]
+//!     host: String,
+//!     #[sdl(attr)]
+//!     tls: Option<bool>,
+//!     port: i32,
+//! }
+//!
+//! let server: Server = convert::from_tag(sdl! {
+//!     server "localhost" tls=true { port 8080; }
+//! }.remove(0)).unwrap();
+//!
+//! assert_eq!((server.host.as_str(), server.tls, server.port), ("localhost", Some(true), 8080));
+//! ```
+
+use crate::parser::{Tag, Value};
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::iter::FromIterator;
+
+/// Types that can be read from a tag.
+pub trait FromSdl: Sized {
+    fn from_tag(tag: Tag) -> Result<Self, ConvertError>;
+
+    /// The value used when there is no tag to read from, if any.
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+/// Types that can be written as a tag.
+pub trait ToSdl {
+    fn to_tag(&self, name: &str) -> Tag;
+
+    /// The tag written for a child field, or `None` to leave it out.
+    fn to_child(&self, name: &str) -> Option<Tag> {
+        Some(self.to_tag(name))
+    }
+}
+
+/// Types that can be read from a single value.
+pub trait FromValue: Sized {
+    /// Describes the expected value in errors.
+    const EXPECTED: &'static str;
+
+    fn from_value(value: Value, numeric: Numeric) -> Result<Self, ValueError>;
+
+    /// The value used when there is no value to read from, if any.
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+/// Types that can be written as a single value.
+pub trait ToValue {
+    /// The value to write, or `None` to leave it out.
+    fn to_value(&self) -> Option<Value>;
+}
+
+/// How numbers convert to Rust types other than their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Numeric {
+    /// Only `Integer` reads as `i32`, `Long` as `i64` and `Float` as `f64`.
+    Exact,
+    /// Numbers also convert when no information is lost: an `Integer` to
+    /// `i64` or `f64`, and a `Long` to `i32` or `f64` when it fits exactly.
+    #[default]
+    Lossless,
+}
+
+/// A value that cannot be converted to the requested type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueError {
+    /// The value has another type, as named by `Value::type_name`.
+    Type {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The value is a number that does not fit in the target type.
+    OutOfRange { target: &'static str },
+}
+
+impl ValueError {
+    fn expected(expected: &'static str, found: &Value) -> Self {
+        ValueError::Type {
+            expected,
+            found: found.type_name(),
+        }
+    }
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValueError::Type { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            ValueError::OutOfRange { target } => write!(f, "number out of range for {}", target),
+        }
+    }
+}
+
+impl error::Error for ValueError {}
+
+/// A tag that does not have the shape a type expects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertError {
+    message: String,
+    line: Option<usize>,
+    source: Option<ValueError>,
+}
+
+impl ConvertError {
+    pub fn new(message: impl Into<String>, line: Option<usize>) -> Self {
+        ConvertError {
+            message: message.into(),
+            line,
+            source: None,
+        }
+    }
+
+    // An error for the value `what` that could not be converted.
+    fn value(what: String, error: ValueError, line: Option<usize>) -> Self {
+        ConvertError {
+            message: format!("{}: {}", what, error),
+            line,
+            source: Some(error),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The line of the offending tag, when it was read by a `Parser`.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{} at line {}", self.message, line),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl error::Error for ConvertError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|error| error as &(dyn error::Error + 'static))
+    }
+}
+
+/// Reads a value of type `T` from `tag`.
+pub fn from_tag<T: FromSdl>(tag: Tag) -> Result<T, ConvertError> {
+    T::from_tag(tag)
+}
+
+/// Reads a value of type `T` from a whole document, whose top level tags
+/// are treated as the children of a nameless tag.
+pub fn from_document<T: FromSdl>(tags: Vec<Tag>) -> Result<T, ConvertError> {
+    let mut root = Tag::new(String::new());
+    *root.children_mut() = tags;
+    T::from_tag(root)
+}
+
+/// Writes `value` as a whole document. The inverse of `from_document`.
+pub fn to_document<T: ToSdl>(value: &T) -> Vec<Tag> {
+    std::mem::take(value.to_tag("").children_mut())
+}
+
+// The functions below are called by derived implementations.
+
+#[doc(hidden)]
+pub fn read_value<T: FromValue>(
+    value: Option<Value>,
+    field: &str,
+    line: Option<usize>,
+) -> Result<T, ConvertError> {
+    match value {
+        Some(value) => T::from_value(value, Numeric::default())
+            .map_err(|error| ConvertError::value(format!("value `{}`", field), error, line)),
+        None => T::missing().ok_or_else(|| {
+            let message = format!("expected {} value `{}`", T::EXPECTED, field);
+            ConvertError::new(message, line)
+        }),
+    }
+}
+
+#[doc(hidden)]
+pub fn read_attribute<T: FromValue>(tag: &mut Tag, name: &str) -> Result<T, ConvertError> {
+    match tag.remove_attribute(name) {
+        Some(value) => T::from_value(value, Numeric::default()).map_err(|error| {
+            ConvertError::value(format!("attribute `{}`", name), error, tag.line())
+        }),
+        None => T::missing().ok_or_else(|| {
+            let message = format!("expected {} attribute `{}`", T::EXPECTED, name);
+            ConvertError::new(message, tag.line())
+        }),
+    }
+}
+
+#[doc(hidden)]
+pub fn read_child<T: FromSdl>(tag: &mut Tag, name: &str) -> Result<T, ConvertError> {
+    match tag.children().iter().position(|child| child.name() == name) {
+        Some(index) => T::from_tag(tag.children_mut().remove(index)),
+        None => T::missing()
+            .ok_or_else(|| ConvertError::new(format!("missing child `{}`", name), tag.line())),
+    }
+}
+
+#[doc(hidden)]
+pub fn read_children<T: FromSdl, C: FromIterator<T>>(
+    tag: &mut Tag,
+    name: &str,
+) -> Result<C, ConvertError> {
+    tag.remove_children_named(name)
+        .into_iter()
+        .map(T::from_tag)
+        .collect()
+}
+
+#[doc(hidden)]
+pub fn write_value<T: ToValue>(tag: &mut Tag, value: &T) {
+    tag.push_value(value.to_value().unwrap_or(Value::Null));
+}
+
+#[doc(hidden)]
+pub fn write_attribute<T: ToValue>(tag: &mut Tag, name: &str, value: &T) {
+    if let Some(value) = value.to_value() {
+        tag.set_attribute(name, value);
+    }
+}
+
+#[doc(hidden)]
+pub fn write_child<T: ToSdl>(tag: &mut Tag, name: &str, value: &T) {
+    if let Some(child) = value.to_child(name) {
+        tag.add_child(child);
+    }
+}
+
+#[doc(hidden)]
+pub fn write_rest(tag: &mut Tag, rest: &Tag) {
+    tag.values_mut().extend(rest.values().iter().cloned());
+    for (name, value) in rest.attributes() {
+        tag.set_attribute(name.clone(), value.clone());
+    }
+    tag.children_mut().extend(rest.children().iter().cloned());
+}
+
+impl FromValue for String {
+    const EXPECTED: &'static str = "string";
+
+    fn from_value(value: Value, _numeric: Numeric) -> Result<Self, ValueError> {
+        match value {
+            Value::String(v) => Ok(v),
+            value => Err(ValueError::expected(Self::EXPECTED, &value)),
+        }
+    }
+}
+
+impl FromValue for bool {
+    const EXPECTED: &'static str = "boolean";
+
+    fn from_value(value: Value, _numeric: Numeric) -> Result<Self, ValueError> {
+        match value {
+            Value::Boolean(v) => Ok(v),
+            value => Err(ValueError::expected(Self::EXPECTED, &value)),
+        }
+    }
+}
+
+impl FromValue for i32 {
+    const EXPECTED: &'static str = "integer";
+
+    fn from_value(value: Value, numeric: Numeric) -> Result<Self, ValueError> {
+        match (value, numeric) {
+            (Value::Integer(v, _), _) => Ok(v),
+            (Value::Long(v, _), Numeric::Lossless) => {
+                i32::try_from(v).map_err(|_| ValueError::OutOfRange { target: "i32" })
+            }
+            (value, _) => Err(ValueError::expected(Self::EXPECTED, &value)),
+        }
+    }
+}
+
+impl FromValue for i64 {
+    const EXPECTED: &'static str = "integer";
+
+    fn from_value(value: Value, numeric: Numeric) -> Result<Self, ValueError> {
+        match (value, numeric) {
+            (Value::Long(v, _), _) => Ok(v),
+            (Value::Integer(v, _), Numeric::Lossless) => Ok(i64::from(v)),
+            (value, _) => Err(ValueError::expected(Self::EXPECTED, &value)),
+        }
+    }
+}
+
+// Integers up to 2^53 in magnitude are exactly representable as `f64`.
+const MAX_EXACT_FLOAT: i64 = 1 << 53;
+
+impl FromValue for f64 {
+    const EXPECTED: &'static str = "float";
+
+    fn from_value(value: Value, numeric: Numeric) -> Result<Self, ValueError> {
+        match (value, numeric) {
+            (Value::Float(v), _) => Ok(v),
+            (Value::Integer(v, _), Numeric::Lossless) => Ok(f64::from(v)),
+            (Value::Long(v, _), Numeric::Lossless)
+                if v.unsigned_abs() <= MAX_EXACT_FLOAT as u64 =>
+            {
+                Ok(v as f64)
+            }
+            (Value::Long(..), Numeric::Lossless) => Err(ValueError::OutOfRange { target: "f64" }),
+            (value, _) => Err(ValueError::expected(Self::EXPECTED, &value)),
+        }
+    }
+}
+
+impl FromValue for Value {
+    const EXPECTED: &'static str = "any";
+
+    fn from_value(value: Value, _numeric: Numeric) -> Result<Self, ValueError> {
+        Ok(value)
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    const EXPECTED: &'static str = T::EXPECTED;
+
+    fn from_value(value: Value, numeric: Numeric) -> Result<Self, ValueError> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value, numeric).map(Some),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+macro_rules! try_from_value {
+    ($($ty:ty),+) => {
+        $(
+            impl TryFrom<Value> for $ty {
+                type Error = ValueError;
+
+                fn try_from(value: Value) -> Result<Self, ValueError> {
+                    <$ty>::from_value(value, Numeric::default())
+                }
+            }
+
+            impl TryFrom<&Value> for $ty {
+                type Error = ValueError;
+
+                fn try_from(value: &Value) -> Result<Self, ValueError> {
+                    <$ty>::from_value(value.clone(), Numeric::default())
+                }
+            }
+        )+
+    };
+}
+
+try_from_value!(String, bool, i32, i64, f64);
+
+impl<'a> TryFrom<&'a Value> for &'a str {
+    type Error = ValueError;
+
+    fn try_from(value: &'a Value) -> Result<Self, ValueError> {
+        match value {
+            Value::String(v) => Ok(v),
+            value => Err(ValueError::expected("string", value)),
+        }
+    }
+}
+
+macro_rules! to_value {
+    ($($ty:ty),+) => {
+        $(
+            impl ToValue for $ty {
+                fn to_value(&self) -> Option<Value> {
+                    Some(Value::from(self.clone()))
+                }
+            }
+        )+
+    };
+}
+
+to_value!(String, i32, i64, f64, bool);
+
+impl ToValue for Value {
+    fn to_value(&self) -> Option<Value> {
+        Some(self.clone())
+    }
+}
+
+impl<T: ToValue> ToValue for Option<T> {
+    fn to_value(&self) -> Option<Value> {
+        self.as_ref().and_then(ToValue::to_value)
+    }
+}
+
+// Plain values are read from and written as tags with a single value, like
+// `port 8080;`.
+macro_rules! single_value {
+    ($($ty:ty),+) => {
+        $(
+            impl FromSdl for $ty {
+                fn from_tag(mut tag: Tag) -> Result<Self, ConvertError> {
+                    let mut values = std::mem::take(tag.values_mut()).into_iter();
+
+                    match (values.next(), values.next()) {
+                        (Some(value), None) => read_value(Some(value), tag.name(), tag.line()),
+                        _ => Err(ConvertError::new(
+                            format!(
+                                "expected a single {} value in `{}`",
+                                <$ty>::EXPECTED,
+                                tag.name()
+                            ),
+                            tag.line(),
+                        )),
+                    }
+                }
+            }
+
+            impl ToSdl for $ty {
+                fn to_tag(&self, name: &str) -> Tag {
+                    let mut tag = Tag::new(String::from(name));
+                    write_value(&mut tag, self);
+                    tag
+                }
+            }
+        )+
+    };
+}
+
+single_value!(String, i32, i64, f64, bool, Value);
+
+impl<T: FromSdl> FromSdl for Option<T> {
+    fn from_tag(tag: Tag) -> Result<Self, ConvertError> {
+        T::from_tag(tag).map(Some)
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: ToSdl> ToSdl for Option<T> {
+    fn to_tag(&self, name: &str) -> Tag {
+        match self {
+            Some(value) => value.to_tag(name),
+            None => Tag::new(String::from(name)),
+        }
+    }
+
+    fn to_child(&self, name: &str) -> Option<Tag> {
+        self.as_ref().map(|value| value.to_tag(name))
+    }
+}
+
+impl FromSdl for Tag {
+    fn from_tag(tag: Tag) -> Result<Self, ConvertError> {
+        Ok(tag)
+    }
+}
+
+impl ToSdl for Tag {
+    fn to_tag(&self, name: &str) -> Tag {
+        let mut tag = self.clone();
+        tag.set_name(name);
+        tag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Radix;
+
+    #[test]
+    fn from_rust_values() {
+        assert_eq!(Value::from("a"), Value::String(String::from("a")));
+        assert_eq!(Value::from(1), Value::Integer(1, Radix::Decimal));
+        assert_eq!(Value::from(1.5), Value::Float(1.5));
+        assert_eq!(Value::from(Some(true)), Value::Boolean(true));
+        assert_eq!(Value::from(None::<i64>), Value::Null);
+    }
+
+    #[test]
+    fn try_from_values() {
+        let string = Value::from("a");
+
+        assert_eq!(<&str>::try_from(&string), Ok("a"));
+        assert_eq!(String::try_from(string.clone()), Ok(String::from("a")));
+        assert_eq!(bool::try_from(&Value::Boolean(true)), Ok(true));
+        assert_eq!(
+            i32::try_from(&string),
+            Err(ValueError::Type {
+                expected: "integer",
+                found: "string"
+            })
+        );
+        assert_eq!(
+            f64::try_from(&Value::Null).unwrap_err().to_string(),
+            "expected float, found null"
+        );
+    }
+
+    #[test]
+    fn numeric_widening() {
+        let int = Value::Integer(7, Radix::Decimal);
+        let long = Value::Long(7, Radix::Decimal);
+        let big = Value::Long(1 << 60, Radix::Decimal);
+
+        assert_eq!(i64::try_from(&int), Ok(7));
+        assert_eq!(f64::try_from(&int), Ok(7.0));
+        assert_eq!(i32::try_from(&long), Ok(7));
+        assert_eq!(
+            i32::try_from(&big),
+            Err(ValueError::OutOfRange { target: "i32" })
+        );
+        assert_eq!(
+            f64::try_from(&big),
+            Err(ValueError::OutOfRange { target: "f64" })
+        );
+        assert_eq!(
+            f64::try_from(&Value::Long(i64::MIN, Radix::Decimal)),
+            Err(ValueError::OutOfRange { target: "f64" })
+        );
+        assert_eq!(
+            f64::try_from(&Value::Long(-(1 << 53), Radix::Decimal)),
+            Ok(-9007199254740992.0)
+        );
+
+        assert_eq!(int.get::<i32>(Numeric::Exact), Ok(7));
+        assert!(int.get::<i64>(Numeric::Exact).is_err());
+        assert!(int.get::<f64>(Numeric::Exact).is_err());
+        assert!(long.get::<i32>(Numeric::Exact).is_err());
+    }
+
+    #[test]
+    fn type_names() {
+        assert_eq!(Value::Long(1, Radix::Hexadecimal).type_name(), "integer");
+        assert_eq!(Value::Float(1.0).type_name(), "float");
+        assert_eq!(Value::Null.type_name(), "null");
+    }
+}
@@ -0,0 +1,514 @@
+//! Edits of SDL source text that keep everything but the edited part as it
+//! was, comments and layout included.
+//!
+//! Tags are found by path, as in `visit::find`, and every tag at the path is
+//! edited. Documents are read with the default `ParserOptions`.
+
+use crate::parser::{Error, Parser, Tag, Value};
+use crate::scanner::{Scanner, Token};
+use crate::writer::{self, WriteError};
+use std::{error, fmt};
+
+const INDENT: &str = "  ";
+
+/// Why an edit could not be made.
+#[derive(Debug, PartialEq)]
+pub enum EditError {
+    /// The document does not parse.
+    Syntax(Error),
+    /// No tag, or no attribute, is at the path.
+    NotFound(String),
+    /// The new text cannot be written as SDL.
+    Write(WriteError),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::Syntax(error) => write!(f, "{}", error),
+            EditError::NotFound(path) => write!(f, "nothing found at `{}`", path),
+            EditError::Write(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl error::Error for EditError {}
+
+impl From<Error> for EditError {
+    fn from(error: Error) -> Self {
+        EditError::Syntax(error)
+    }
+}
+
+impl From<WriteError> for EditError {
+    fn from(error: WriteError) -> Self {
+        EditError::Write(error)
+    }
+}
+
+/// Replaces the values of the tags at `path` with `values`.
+pub fn set_values(source: &str, path: &str, values: &[Value]) -> Result<String, EditError> {
+    let text: Vec<String> = values
+        .iter()
+        .map(writer::write_value)
+        .collect::<Result<_, _>>()?;
+    let text = text.join(" ");
+
+    splice(source, path, |tag, splices| {
+        match tag.values.split_first() {
+            Some((first, rest)) if !text.is_empty() => {
+                splices.push((first.start, first.end, text.clone()));
+                for value in rest {
+                    splices.push((value.before, value.end, String::new()));
+                }
+            }
+            Some(_) => {
+                for value in &tag.values {
+                    splices.push((value.before, value.end, String::new()));
+                }
+            }
+            None if !text.is_empty() => {
+                splices.push((tag.name_end, tag.name_end, format!(" {}", text)))
+            }
+            None => (),
+        }
+    })
+}
+
+/// Sets attribute `name` of the tags at `path` to `value`, adding it after
+/// their other values and attributes if they do not have it. A repeated
+/// attribute is rewritten where it last appears and removed elsewhere.
+pub fn set_attribute(
+    source: &str,
+    path: &str,
+    name: &str,
+    value: &Value,
+) -> Result<String, EditError> {
+    writer::check_name(name)?;
+    let text = format!("{}={}", name, writer::write_value(value)?);
+
+    splice(source, path, |tag, splices| {
+        let attributes: Vec<_> = tag.attributes(name).collect();
+
+        match attributes.split_last() {
+            Some((last, rest)) => {
+                splices.push((last.start, last.end, text.clone()));
+                for attribute in rest {
+                    splices.push((attribute.before, attribute.end, String::new()));
+                }
+            }
+            None => splices.push((tag.end, tag.end, format!(" {}", text))),
+        }
+    })
+}
+
+/// Removes every occurrence of attribute `name` from the tags at `path`.
+pub fn remove_attribute(source: &str, path: &str, name: &str) -> Result<String, EditError> {
+    let result = splice(source, path, |tag, splices| {
+        for attribute in tag.attributes(name) {
+            splices.push((attribute.before, attribute.end, String::new()));
+        }
+    })?;
+
+    if result == source {
+        return Err(EditError::NotFound(format!("{}@{}", path, name)));
+    }
+    Ok(result)
+}
+
+/// Adds `child` as the last child of the tags at `path`, or as the last tag
+/// of the document if `path` is empty or `/`.
+pub fn add_child(source: &str, path: &str, child: &Tag) -> Result<String, EditError> {
+    let written = writer::write(std::slice::from_ref(child))?;
+
+    if path.trim_matches('/').is_empty() {
+        Parser::from_str(source).parse()?;
+
+        let mut result = String::from(source);
+        if !result.is_empty() && !result.ends_with('\n') {
+            result.push('\n');
+        }
+        result.push_str(&indented(&written, ""));
+        return Ok(result);
+    }
+
+    splice(source, path, |tag, splices| {
+        let indent = line_indent(source, tag.start);
+
+        match tag.body {
+            Some(close) => {
+                // Children on the line of their parent say nothing about
+                // indentation.
+                let child_indent = match tag.first_child {
+                    Some(start) if line_start(source, start) != line_start(source, tag.start) => {
+                        line_indent(source, start)
+                    }
+                    _ => format!("{}{}", indent, INDENT),
+                };
+                let text = indented(&written, &child_indent);
+                let line_start = line_start(source, close);
+
+                if source[line_start..close].trim().is_empty() {
+                    splices.push((line_start, line_start, text));
+                } else {
+                    // `a { b; }`: the closing brace goes on a line of its own.
+                    let end = source[..close].trim_end().len();
+                    splices.push((end, close, format!("\n{}{}", text, indent)));
+                }
+            }
+            None => {
+                let text = indented(&written, &format!("{}{}", indent, INDENT));
+                let body = format!(" {{\n{}{}}}", text, indent);
+
+                match tag.semicolon {
+                    Some((start, end)) => splices.push((start, end, body)),
+                    None => splices.push((tag.end, tag.end, body)),
+                }
+            }
+        }
+    })
+}
+
+/// Removes the tags at `path`. A tag on a line of its own is removed with
+/// the line, including a comment at its end.
+pub fn delete(source: &str, path: &str) -> Result<String, EditError> {
+    splice(source, path, |tag, splices| {
+        let line_start = line_start(source, tag.start);
+        let line_end = source[tag.stop..]
+            .find('\n')
+            .map_or(source.len(), |i| tag.stop + i);
+        let before = &source[line_start..tag.start];
+        let after = source[tag.stop..line_end].trim();
+
+        if before.trim().is_empty() && (after.is_empty() || is_comment(after)) {
+            splices.push((line_start, (line_end + 1).min(source.len()), String::new()));
+        } else if after.is_empty() || is_comment(after) {
+            let start = source[..tag.start].trim_end_matches([' ', '\t']).len();
+            splices.push((start, tag.stop, String::new()));
+        } else {
+            let rest = &source[tag.stop..];
+            let end = tag.stop + (rest.len() - rest.trim_start_matches([' ', '\t']).len());
+            splices.push((tag.start, end, String::new()));
+        }
+    })
+}
+
+// Where the parts of a tag are in the source.
+struct Located {
+    path: String,
+    start: usize,
+    name_end: usize,
+    values: Vec<Span>,
+    attributes: Vec<(String, Span)>,
+    // End of the name or of the last value or attribute.
+    end: usize,
+    semicolon: Option<(usize, usize)>,
+    // Start of the `}` closing the body.
+    body: Option<usize>,
+    first_child: Option<usize>,
+    // End of the whole tag, including its `;` or body.
+    stop: usize,
+}
+
+struct Span {
+    // End of the token before this one, so that `before..end` removes the
+    // span and the space in front of it.
+    before: usize,
+    start: usize,
+    end: usize,
+}
+
+impl Located {
+    fn attributes<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Span> {
+        self.attributes
+            .iter()
+            .filter(move |(attribute, _)| attribute == name)
+            .map(|(_, span)| span)
+    }
+}
+
+// Applies the splices `edit` makes for each tag at `path`. Each splice
+// replaces `start..end` with its text.
+fn splice<F>(source: &str, path: &str, mut edit: F) -> Result<String, EditError>
+where
+    F: FnMut(&Located, &mut Vec<(usize, usize, String)>),
+{
+    Parser::from_str(source).parse()?;
+
+    let path = path.trim_matches('/');
+    let tags = locate(source);
+    let mut splices = Vec::new();
+    let mut found = false;
+
+    for tag in tags.iter().filter(|tag| tag.path == path) {
+        found = true;
+        edit(tag, &mut splices);
+    }
+
+    if !found {
+        return Err(EditError::NotFound(String::from(path)));
+    }
+
+    // Tags at the same path never contain each other, so the splices do not
+    // overlap.
+    splices.sort_by_key(|splice| std::cmp::Reverse(splice.0));
+
+    let mut result = String::from(source);
+    for (start, end, text) in splices {
+        result.replace_range(start..end, &text);
+    }
+    Ok(result)
+}
+
+// Finds the tags of a document that is known to parse.
+fn locate(source: &str) -> Vec<Located> {
+    let mut tags: Vec<Located> = Vec::new();
+    // Tags whose body is open.
+    let mut open: Vec<usize> = Vec::new();
+    let mut current: Option<usize> = None;
+    let mut attribute: Option<(String, usize, usize)> = None;
+
+    for token in Scanner::new(source) {
+        let (start, end, _) = token.position();
+
+        match token {
+            Token::Identifier(..) => match current {
+                Some(i) => {
+                    attribute = Some((String::from(&source[start..end]), tags[i].end, start))
+                }
+                None => {
+                    let name = &source[start..end];
+                    let path = match open.last() {
+                        Some(&parent) => {
+                            tags[parent].first_child.get_or_insert(start);
+                            format!("{}/{}", tags[parent].path, name)
+                        }
+                        None => String::from(name),
+                    };
+
+                    current = Some(tags.len());
+                    tags.push(Located {
+                        path,
+                        start,
+                        name_end: end,
+                        values: Vec::new(),
+                        attributes: Vec::new(),
+                        end,
+                        semicolon: None,
+                        body: None,
+                        first_child: None,
+                        stop: end,
+                    });
+                }
+            },
+            Token::String(..)
+            | Token::Integer(..)
+            | Token::Float64(..)
+            | Token::True(..)
+            | Token::False(..)
+            | Token::Null(..) => {
+                let tag = match current {
+                    Some(i) => &mut tags[i],
+                    None => continue,
+                };
+                // The span of a string leaves out its quotes.
+                let (start, end) = match token {
+                    Token::String(..) => (start - 1, end + 1),
+                    _ => (start, end),
+                };
+
+                match attribute.take() {
+                    Some((name, before, name_start)) => tag.attributes.push((
+                        name,
+                        Span {
+                            before,
+                            start: name_start,
+                            end,
+                        },
+                    )),
+                    None => tag.values.push(Span {
+                        before: tag.end,
+                        start,
+                        end,
+                    }),
+                }
+                tag.end = end;
+                tag.stop = end;
+            }
+            Token::Semicolon(..) => {
+                if let Some(i) = current.take() {
+                    tags[i].semicolon = Some((start, end));
+                    tags[i].stop = end;
+                }
+            }
+            Token::LeftBrace(..) => {
+                if let Some(i) = current.take() {
+                    open.push(i);
+                }
+            }
+            Token::RightBrace(..) => {
+                current = None;
+                if let Some(i) = open.pop() {
+                    tags[i].body = Some(start);
+                    tags[i].stop = end;
+                }
+            }
+            Token::Equal(..) | Token::Comment(..) | Token::Error(..) | Token::Eof(..) => (),
+        }
+    }
+
+    tags
+}
+
+// `written` with every line indented by `indent`.
+fn indented(written: &str, indent: &str) -> String {
+    written
+        .lines()
+        .map(|line| format!("{}{}\n", indent, line))
+        .collect()
+}
+
+fn line_start(source: &str, index: usize) -> usize {
+    source[..index].rfind('\n').map_or(0, |i| i + 1)
+}
+
+// The whitespace at the start of the line containing `index`.
+fn line_indent(source: &str, index: usize) -> String {
+    let line = &source[line_start(source, index)..];
+    let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+    String::from(&line[..indent])
+}
+
+fn is_comment(text: &str) -> bool {
+    ["//", "#", "--"]
+        .iter()
+        .any(|marker| text.starts_with(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "// manifest\nname \"react-easy-flip\";\nversion \"4.0.3\"; // bumped by CI\n\nscripts {\n    build \"a\" \"b\" dev=true;  # keep\n    lint;\n}\n";
+
+    #[test]
+    fn sets_values() {
+        assert_eq!(
+            set_values(SOURCE, "version", &[Value::from("4.0.4")]).unwrap(),
+            SOURCE.replace("\"4.0.3\"", "\"4.0.4\"")
+        );
+        assert_eq!(
+            set_values(SOURCE, "scripts/build", &[Value::from(1)]).unwrap(),
+            SOURCE.replace("\"a\" \"b\"", "1")
+        );
+        assert_eq!(
+            set_values(SOURCE, "scripts/lint", &[Value::from(true), Value::Null]).unwrap(),
+            SOURCE.replace("lint;", "lint true null;")
+        );
+        assert_eq!(
+            set_values(SOURCE, "scripts/build", &[]).unwrap(),
+            SOURCE.replace("build \"a\" \"b\" dev", "build dev")
+        );
+    }
+
+    #[test]
+    fn sets_attributes() {
+        assert_eq!(
+            set_attribute(SOURCE, "scripts/build", "dev", &Value::from(false)).unwrap(),
+            SOURCE.replace("dev=true", "dev=false")
+        );
+        assert_eq!(
+            set_attribute(SOURCE, "scripts/lint", "fix", &Value::from(true)).unwrap(),
+            SOURCE.replace("lint;", "lint fix=true;")
+        );
+        assert_eq!(
+            remove_attribute(SOURCE, "scripts/build", "dev").unwrap(),
+            SOURCE.replace(" dev=true", "")
+        );
+        assert_eq!(
+            remove_attribute(SOURCE, "scripts/lint", "dev"),
+            Err(EditError::NotFound(String::from("scripts/lint@dev")))
+        );
+    }
+
+    #[test]
+    fn edits_repeated_attributes() {
+        let source = "server port=80 host=\"a\" port=8080;\n";
+
+        assert_eq!(
+            set_attribute(source, "server", "port", &Value::from(443)).unwrap(),
+            "server host=\"a\" port=443;\n"
+        );
+        assert_eq!(
+            remove_attribute(source, "server", "port").unwrap(),
+            "server host=\"a\";\n"
+        );
+    }
+
+    #[test]
+    fn adds_children() {
+        let child = Tag::build("test").value("jest").build();
+
+        assert_eq!(
+            add_child(SOURCE, "scripts", &child).unwrap(),
+            SOURCE.replace("    lint;\n", "    lint;\n    test \"jest\";\n")
+        );
+        assert_eq!(
+            add_child(SOURCE, "name", &child).unwrap(),
+            SOURCE.replace(
+                "name \"react-easy-flip\";",
+                "name \"react-easy-flip\" {\n  test \"jest\";\n}"
+            )
+        );
+        assert_eq!(
+            add_child(SOURCE, "", &child).unwrap(),
+            format!("{}test \"jest\";\n", SOURCE)
+        );
+        assert_eq!(
+            add_child("a { b; }", "a", &child).unwrap(),
+            "a { b;\n  test \"jest\";\n}"
+        );
+        assert_eq!(
+            add_child("a {\n}\nb;", "a", &child).unwrap(),
+            "a {\n  test \"jest\";\n}\nb;"
+        );
+    }
+
+    #[test]
+    fn deletes_tags() {
+        assert_eq!(
+            delete(SOURCE, "version").unwrap(),
+            SOURCE.replace("version \"4.0.3\"; // bumped by CI\n", "")
+        );
+        assert_eq!(
+            delete(SOURCE, "scripts").unwrap(),
+            "// manifest\nname \"react-easy-flip\";\nversion \"4.0.3\"; // bumped by CI\n\n"
+        );
+        assert_eq!(delete("a; b; c;", "b").unwrap(), "a; c;");
+        assert_eq!(delete("a; b;\n", "b").unwrap(), "a;\n");
+        assert_eq!(delete("a { b 1; b 2; }", "a/b").unwrap(), "a { }");
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(
+            delete(SOURCE, "scripts/test"),
+            Err(EditError::NotFound(String::from("scripts/test")))
+        );
+        assert!(matches!(delete("a =;", "a"), Err(EditError::Syntax(_))));
+        assert_eq!(
+            set_values(SOURCE, "name", &[Value::String(String::from("a \"b\""))]),
+            Err(EditError::Write(WriteError::String(String::from(
+                "a \"b\""
+            ))))
+        );
+        assert_eq!(
+            set_attribute(SOURCE, "name", "a b", &Value::Null),
+            Err(EditError::Write(WriteError::Name(String::from("a b"))))
+        );
+        assert_eq!(
+            add_child(SOURCE, "", &Tag::new(String::new())),
+            Err(EditError::Write(WriteError::Name(String::new())))
+        );
+    }
+}
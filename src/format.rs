@@ -0,0 +1,373 @@
+use crate::options::ParserOptions;
+use crate::parser::{Error, Parser};
+use crate::scanner::{Scanner, Token, TokenSource};
+
+/// How `format` lays out a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    /// Indentation of one level of nesting.
+    pub indent: String,
+    /// How tags are ended.
+    pub terminator: Terminator,
+    /// Marker comments are rewritten with, or `None` to keep each comment's
+    /// own.
+    pub comment_marker: Option<CommentMarker>,
+    /// Write attributes in name order rather than in source order.
+    pub sort_attributes: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            indent: String::from("  "),
+            terminator: Terminator::default(),
+            comment_marker: Some(CommentMarker::default()),
+            sort_attributes: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Terminator {
+    /// `name value;`
+    #[default]
+    Semicolon,
+    /// `name value` followed by a newline. Reading the result back needs
+    /// `ParserOptions::newline_terminators`.
+    Newline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CommentMarker {
+    /// `// comment`
+    #[default]
+    DoubleSlash,
+    /// `# comment`
+    Hash,
+    /// `-- comment`
+    DoubleDash,
+}
+
+impl CommentMarker {
+    fn as_str(self) -> &'static str {
+        match self {
+            CommentMarker::DoubleSlash => "//",
+            CommentMarker::Hash => "#",
+            CommentMarker::DoubleDash => "--",
+        }
+    }
+}
+
+/// Rewrites `source` in a canonical layout: one tag per line, nested tags
+/// indented, values before attributes, single spaces between items and no
+/// spaces around `=`. Comments are kept, each on its own line or at the end
+/// of the line it followed, and single blank lines between tags are kept.
+///
+/// `source` is read with `options`; documents that do not parse are
+/// returned as the parse error. Formatting a formatted document with the
+/// same style returns it unchanged.
+pub fn format(source: &str, options: ParserOptions, style: &Style) -> Result<String, Error> {
+    Parser::with_options(Scanner::new(source), options).parse()?;
+
+    let mut scanner = Scanner::new(source).keep_comments();
+    scanner.configure(&options);
+
+    let mut formatter = Formatter {
+        source,
+        style,
+        out: String::new(),
+        depth: 0,
+        tag: None,
+        attribute: None,
+        last_line: 0,
+        opened: true,
+    };
+
+    for token in scanner.by_ref() {
+        formatter.token(token);
+    }
+    // Only a document read with newline terminators can end inside a tag.
+    let (_, _, line) = scanner.eof().position();
+    formatter.end_tag(formatter.terminator(), line);
+
+    Ok(formatter.out)
+}
+
+// A tag whose line has not been written yet.
+struct Line {
+    name: String,
+    values: Vec<String>,
+    attributes: Vec<(String, String)>,
+    // With the source line each is on.
+    comments: Vec<(String, usize)>,
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    style: &'a Style,
+    out: String,
+    depth: usize,
+    tag: Option<Line>,
+    // Name of an attribute waiting for its value.
+    attribute: Option<String>,
+    // Source line of the last token written.
+    last_line: usize,
+    // Whether nothing was written since the start or the last `{`.
+    opened: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn token(&mut self, token: Token) {
+        let (start, end, line) = token.position();
+        let text = &self.source[start..end];
+
+        match token {
+            Token::Identifier(..) => match self.tag {
+                Some(_) => self.attribute = Some(String::from(text)),
+                None => {
+                    self.separate(line);
+                    self.tag = Some(Line {
+                        name: String::from(text),
+                        values: Vec::new(),
+                        attributes: Vec::new(),
+                        comments: Vec::new(),
+                    });
+                }
+            },
+            Token::String(..) => self.literal(format!("\"{}\"", text)),
+            Token::Integer(..)
+            | Token::Float64(..)
+            | Token::True(..)
+            | Token::False(..)
+            | Token::Null(..) => self.literal(String::from(text)),
+            Token::Semicolon(..) => {
+                if self.end_tag(self.terminator(), line) {
+                    self.last_line = line;
+                }
+            }
+            Token::LeftBrace(..) => {
+                self.end_tag(" {", line);
+                self.last_line = line;
+                self.depth += 1;
+                self.opened = true;
+            }
+            Token::RightBrace(..) => {
+                self.depth = self.depth.saturating_sub(1);
+                self.indent();
+                self.out.push_str("}\n");
+                self.last_line = line;
+                self.opened = false;
+            }
+            Token::Comment(..) => {
+                let comment = self.comment(text);
+
+                if let Some(tag) = self.tag.as_mut() {
+                    tag.comments.push((comment, line));
+                } else if line == self.last_line && !self.out.is_empty() {
+                    self.out.pop();
+                    self.out.push(' ');
+                    self.out.push_str(&comment);
+                    self.out.push('\n');
+                } else {
+                    self.separate(line);
+                    self.indent();
+                    self.out.push_str(&comment);
+                    self.out.push('\n');
+                    self.last_line = line;
+                }
+            }
+            Token::Equal(..) | Token::Error(..) | Token::Eof(..) => (),
+        }
+    }
+
+    fn literal(&mut self, text: String) {
+        if let Some(tag) = self.tag.as_mut() {
+            match self.attribute.take() {
+                Some(name) => tag.attributes.push((name, text)),
+                None => tag.values.push(text),
+            }
+        }
+    }
+
+    fn terminator(&self) -> &'static str {
+        match self.style.terminator {
+            Terminator::Semicolon => ";",
+            Terminator::Newline => "",
+        }
+    }
+
+    // Writes the pending tag, if any, ended by `terminator` on source line
+    // `line`. Comments found inside the tag go on their own lines before it,
+    // except for one on the line of the terminator, which ends the line.
+    fn end_tag(&mut self, terminator: &str, line: usize) -> bool {
+        let mut tag = match self.tag.take() {
+            Some(tag) => tag,
+            None => return false,
+        };
+
+        let trailing = match tag.comments.last() {
+            Some((_, last)) if *last == line => tag.comments.pop().map(|(comment, _)| comment),
+            _ => None,
+        };
+        for (comment, _) in &tag.comments {
+            self.indent();
+            self.out.push_str(comment);
+            self.out.push('\n');
+        }
+
+        if self.style.sort_attributes {
+            tag.attributes.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        self.indent();
+        self.out.push_str(&tag.name);
+        for value in &tag.values {
+            self.out.push(' ');
+            self.out.push_str(value);
+        }
+        for (name, value) in &tag.attributes {
+            self.out.push(' ');
+            self.out.push_str(name);
+            self.out.push('=');
+            self.out.push_str(value);
+        }
+        self.out.push_str(terminator);
+        if let Some(comment) = trailing {
+            self.out.push(' ');
+            self.out.push_str(&comment);
+        }
+        self.out.push('\n');
+        self.opened = false;
+        true
+    }
+
+    // Keeps one blank line where the source had any, except at the start of
+    // the document or of a body.
+    fn separate(&mut self, line: usize) {
+        if !self.opened && line > self.last_line + 1 {
+            self.out.push('\n');
+        }
+        self.opened = false;
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str(&self.style.indent);
+        }
+    }
+
+    fn comment(&self, text: &str) -> String {
+        let marker = ["//", "#", "--"]
+            .iter()
+            .find(|marker| text.starts_with(*marker))
+            .copied()
+            .unwrap_or("");
+        let body = text[marker.len()..].trim();
+        let marker = self
+            .style
+            .comment_marker
+            .map_or(marker, CommentMarker::as_str);
+
+        if body.is_empty() {
+            String::from(marker)
+        } else {
+            format!("{} {}", marker, body)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(source: &str, style: &Style) -> String {
+        let formatted = format(source, ParserOptions::default(), style).unwrap();
+        let options = ParserOptions {
+            newline_terminators: style.terminator == Terminator::Newline,
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(
+            format(&formatted, options, style).unwrap(),
+            formatted,
+            "not idempotent"
+        );
+        formatted
+    }
+
+    #[test]
+    fn canonical_layout() {
+        let source = "author   \"Kirill\"  ;name \"x\";\n\n\n\nscripts{build{prod  \"microbundle\"\n;}\n  format k = true \"prettier\"   ;\n}";
+
+        assert_eq!(
+            fmt(source, &Style::default()),
+            "author \"Kirill\";\nname \"x\";\n\nscripts {\n  build {\n    prod \"microbundle\";\n  }\n  format \"prettier\" k=true;\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let source = "# settings\nport 80; -- http\nhost \"a\" \\ #why\n  \"b\"; //  last\nserver { // body\n  tls;\n} // end\n// bye";
+
+        assert_eq!(
+            fmt(source, &Style::default()),
+            "// settings\nport 80; // http\n// why\nhost \"a\" \"b\"; // last\nserver { // body\n  tls;\n} // end\n// bye\n"
+        );
+
+        let keep = Style {
+            comment_marker: None,
+            ..Style::default()
+        };
+        assert_eq!(
+            fmt(source, &keep),
+            "# settings\nport 80; -- http\n# why\nhost \"a\" \"b\"; // last\nserver { // body\n  tls;\n} // end\n// bye\n"
+        );
+    }
+
+    #[test]
+    fn comments_inside_tags() {
+        assert_eq!(
+            fmt("a 1 // one\n 2 // two\n 3;", &Style::default()),
+            "// one\n// two\na 1 2 3;\n"
+        );
+    }
+
+    #[test]
+    fn newline_terminators() {
+        let style = Style {
+            terminator: Terminator::Newline,
+            indent: String::from("\t"),
+            ..Style::default()
+        };
+
+        assert_eq!(
+            fmt("a 1; b { c; // x\n}", &style),
+            "a 1\nb {\n\tc // x\n}\n"
+        );
+    }
+
+    #[test]
+    fn sorts_attributes() {
+        let style = Style {
+            sort_attributes: true,
+            ..Style::default()
+        };
+
+        assert_eq!(fmt("a z=1 m=2 b=3;", &style), "a b=3 m=2 z=1;\n");
+        assert_eq!(fmt("a z=1 m=2 b=3;", &Style::default()), "a z=1 m=2 b=3;\n");
+    }
+
+    #[test]
+    fn rejects_invalid_documents() {
+        assert!(format("a =;", ParserOptions::default(), &Style::default()).is_err());
+    }
+
+    #[test]
+    fn config() {
+        let source = include_str!("../config.sdl");
+        let formatted = fmt(source, &Style::default());
+        let tags = |source: &str| Parser::from_str(source).parse().unwrap();
+
+        assert_eq!(tags(&formatted), tags(source));
+    }
+}
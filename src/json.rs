@@ -0,0 +1,1122 @@
+//! Conversion of tag trees to and from JSON, with two mappings.
+//!
+//! # Canonical
+//!
+//! Lossless: `from_json` reads back exactly the tags `to_json` wrote.
+//!
+//! A document is an array of tags, and a tag is an object with the members
+//! `name`, `values` (an array), `attributes` (an object, in name order) and
+//! `children` (an array of tags), in that order. Empty members are left out,
+//! so `enabled;` is `{"name": "enabled"}`, and so is the name of anonymous
+//! tags. In SDLang, a line that starts with a value is an anonymous tag
+//! named `content`; this crate does not support them, so the parser never
+//! produces one and `writer::write` rejects them.
+//!
+//! Strings, booleans and `null` are written as themselves, decimal integers
+//! as integer numbers and finite floats as numbers with a fraction or an
+//! exponent. Other values are written as an object naming their type:
+//!
+//! | Value                  | JSON                                             |
+//! |------------------------|--------------------------------------------------|
+//! | `Long(5, Decimal)`     | `{"type": "long", "value": 5}`                   |
+//! | `Long(31, Hexadecimal)`| `{"type": "long", "value": 31, "radix": 16}`     |
+//! | `Integer(5, Binary)`   | `{"type": "integer", "value": 5, "radix": 2}`    |
+//! | `Float(NAN)`           | `{"type": "float", "value": "nan"}`              |
+//!
+//! `Value` has no dates or binary data, so there are no types for them.
+//! When reading, an integer number is an `Integer` if it fits in 32 bits and
+//! a `Long` otherwise, and `"radix"` may also be 10.
+//!
+//! # Friendly
+//!
+//! For JSON meant for people and for tools that expect plain objects, like
+//! `package.json`. A list of tags is an object with a property for each tag
+//! name, in the order the names first appear. The property is the tag, or an
+//! array of the tags when several have the name.
+//!
+//! A tag with a single value and nothing else is the value itself, so
+//! `version "4.0.3";` is `"version": "4.0.3"`. Any other tag is an object:
+//! its values in a `$values` array, its attributes as properties named `@`
+//! and the attribute name, and then its children as above:
+//!
+//! ```text
+//! server "a" port=80 { tls; }
+//! ```
+//!
+//! is `"server": {"$values": ["a"], "@port": 80, "tls": {}}`.
+//!
+//! This mapping loses the order between tags of different names, and
+//! children named `$values`, starting with `@` or looking like a typed
+//! value are read back differently.
+
+use crate::parser::{Radix, Tag, Value};
+use crate::writer;
+use std::fmt::Write;
+use std::{error, fmt};
+
+const INDENT: &str = "  ";
+
+// Deeper JSON than this is rejected rather than read, as reading tags from
+// it recurses.
+const MAX_DEPTH: usize = 512;
+
+/// How tags are mapped to JSON. See the module documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mapping {
+    #[default]
+    Canonical,
+    Friendly,
+}
+
+/// JSON that is not valid, or that does not describe tags.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    message: String,
+    line: usize,
+}
+
+impl JsonError {
+    fn new(message: impl Into<String>, line: usize) -> Self {
+        JsonError {
+            message: message.into(),
+            line,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at line {}", self.message, self.line)
+    }
+}
+
+impl error::Error for JsonError {}
+
+/// Writes `tags` as JSON, indented by two spaces per level.
+pub fn to_json(tags: &[Tag], mapping: Mapping) -> String {
+    let mut out = String::new();
+    let mut stack = Vec::new();
+    match mapping {
+        Mapping::Canonical => open_array(&mut out, &mut stack, tags, 0, String::new()),
+        Mapping::Friendly => {
+            open_friendly_object(&mut out, &mut stack, Vec::new(), tags, 0, String::new())
+        }
+    }
+
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Text(text) => out.push_str(&text),
+            Step::Tag(tag, depth) => write_tag(&mut out, &mut stack, tag, depth),
+            Step::Friendly(tag, depth) => write_friendly_tag(&mut out, &mut stack, tag, depth),
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// Reads tags from JSON written with `mapping`.
+pub fn from_json(text: &str, mapping: Mapping) -> Result<Vec<Tag>, JsonError> {
+    let mut reader = Reader {
+        text,
+        position: 0,
+        line: 1,
+    };
+    let json = reader.document()?;
+
+    match mapping {
+        Mapping::Canonical => match json.kind {
+            Kind::Array(items) => items.into_iter().map(read_tag).collect(),
+            _ => Err(JsonError::new("expected an array of tags", json.line)),
+        },
+        Mapping::Friendly => match json.kind {
+            Kind::Object(members) => read_friendly_tags(members),
+            _ => Err(JsonError::new("expected an object", json.line)),
+        },
+    }
+}
+
+// What is left to write, kept on an explicit stack like the tags of
+// `Parser`, so that deeply nested tags do not overflow the call stack.
+enum Step<'a> {
+    Text(String),
+    Tag(&'a Tag, usize),
+    Friendly(&'a Tag, usize),
+}
+
+// Writes the start of an array of `tags` at `depth` and pushes the rest of
+// it, followed by `close`.
+fn open_array<'a>(
+    out: &mut String,
+    stack: &mut Vec<Step<'a>>,
+    tags: &'a [Tag],
+    depth: usize,
+    close: String,
+) {
+    if tags.is_empty() {
+        out.push_str("[]");
+        out.push_str(&close);
+        return;
+    }
+
+    out.push_str("[\n");
+    let mut steps = Vec::new();
+    for (i, tag) in tags.iter().enumerate() {
+        if i > 0 {
+            steps.push(Step::Text(String::from(",\n")));
+        }
+        steps.push(Step::Text(INDENT.repeat(depth + 1)));
+        steps.push(Step::Tag(tag, depth + 1));
+    }
+    steps.push(Step::Text(format!("\n{}]{}", INDENT.repeat(depth), close)));
+    stack.extend(steps.into_iter().rev());
+}
+
+fn write_tag<'a>(out: &mut String, stack: &mut Vec<Step<'a>>, tag: &'a Tag, depth: usize) {
+    let mut members = Vec::new();
+
+    if !tag.name().is_empty() {
+        members.push(format!("\"name\": {}", write_string(tag.name())));
+    }
+    if !tag.values().is_empty() {
+        members.push(format!("\"values\": {}", values(tag)));
+    }
+    if !tag.attributes().is_empty() {
+        let attributes: Vec<String> = sorted_attributes(tag)
+            .into_iter()
+            .map(|(name, v)| format!("{}: {}", write_string(name), write_value(v)))
+            .collect();
+        members.push(format!("\"attributes\": {{{}}}", attributes.join(", ")));
+    }
+    if tag.children().is_empty() {
+        write_object(out, &members, depth);
+        return;
+    }
+
+    out.push_str("{\n");
+    for member in members {
+        out.push_str(&INDENT.repeat(depth + 1));
+        out.push_str(&member);
+        out.push_str(",\n");
+    }
+    out.push_str(&INDENT.repeat(depth + 1));
+    out.push_str("\"children\": ");
+    let close = format!("\n{}}}", INDENT.repeat(depth));
+    open_array(out, stack, tag.children(), depth + 1, close);
+}
+
+// Writes an object with one member per line.
+fn write_object(out: &mut String, members: &[String], depth: usize) {
+    if members.is_empty() {
+        out.push_str("{}");
+        return;
+    }
+
+    out.push_str("{\n");
+    for (i, member) in members.iter().enumerate() {
+        if i > 0 {
+            out.push_str(",\n");
+        }
+        out.push_str(&INDENT.repeat(depth + 1));
+        out.push_str(member);
+    }
+    out.push('\n');
+    out.push_str(&INDENT.repeat(depth));
+    out.push('}');
+}
+
+// Writes the start of the object at `depth` with the `leading` members and
+// a member for each name in `tags` in the friendly mapping, and pushes the
+// rest of it, followed by `close`.
+fn open_friendly_object<'a>(
+    out: &mut String,
+    stack: &mut Vec<Step<'a>>,
+    leading: Vec<String>,
+    tags: &'a [Tag],
+    depth: usize,
+    close: String,
+) {
+    let mut names: Vec<&str> = Vec::new();
+    for tag in tags {
+        if !names.contains(&tag.name()) {
+            names.push(tag.name());
+        }
+    }
+
+    if leading.is_empty() && names.is_empty() {
+        out.push_str("{}");
+        out.push_str(&close);
+        return;
+    }
+
+    out.push_str("{\n");
+    let indent = INDENT.repeat(depth + 1);
+    let mut steps: Vec<Step> = Vec::new();
+    let separator = |steps: &Vec<Step>| if steps.is_empty() { "" } else { ",\n" };
+
+    for member in leading {
+        steps.push(Step::Text(format!(
+            "{}{}{}",
+            separator(&steps),
+            indent,
+            member
+        )));
+    }
+    for name in names {
+        let named: Vec<&Tag> = tags.iter().filter(|tag| tag.name() == name).collect();
+        let text = format!("{}{}{}: ", separator(&steps), indent, write_string(name));
+        steps.push(Step::Text(text));
+
+        if let [tag] = named.as_slice() {
+            steps.push(Step::Friendly(tag, depth + 1));
+        } else {
+            steps.push(Step::Text(String::from("[\n")));
+            for (i, tag) in named.into_iter().enumerate() {
+                if i > 0 {
+                    steps.push(Step::Text(String::from(",\n")));
+                }
+                steps.push(Step::Text(INDENT.repeat(depth + 2)));
+                steps.push(Step::Friendly(tag, depth + 2));
+            }
+            steps.push(Step::Text(format!("\n{}]", indent)));
+        }
+    }
+    steps.push(Step::Text(format!("\n{}}}{}", INDENT.repeat(depth), close)));
+    stack.extend(steps.into_iter().rev());
+}
+
+fn write_friendly_tag<'a>(out: &mut String, stack: &mut Vec<Step<'a>>, tag: &'a Tag, depth: usize) {
+    if let [value] = tag.values() {
+        if tag.attributes().is_empty() && tag.children().is_empty() {
+            out.push_str(&write_value(value));
+            return;
+        }
+    }
+
+    let mut members = Vec::new();
+    if !tag.values().is_empty() {
+        members.push(format!("\"$values\": {}", values(tag)));
+    }
+    for (name, value) in sorted_attributes(tag) {
+        members.push(format!(
+            "{}: {}",
+            write_string(&format!("@{}", name)),
+            write_value(value)
+        ));
+    }
+
+    open_friendly_object(out, stack, members, tag.children(), depth, String::new());
+}
+
+fn values(tag: &Tag) -> String {
+    let values: Vec<String> = tag.values().iter().map(write_value).collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn sorted_attributes(tag: &Tag) -> Vec<(&String, &Value)> {
+    let mut attributes: Vec<_> = tag.attributes().iter().collect();
+    attributes.sort_by(|a, b| a.0.cmp(b.0));
+    attributes
+}
+
+/// Writes a single value as JSON.
+pub fn write_value(value: &Value) -> String {
+    match value {
+        Value::String(v) => write_string(v),
+        Value::Integer(v, Radix::Decimal) => format!("{}", v),
+        Value::Integer(v, radix) => typed("integer", &v.to_string(), *radix),
+        Value::Long(v, radix) => typed("long", &v.to_string(), *radix),
+        Value::Float(v) if v.is_finite() => writer::literal(value),
+        Value::Float(_) => format!(
+            "{{\"type\": \"float\", \"value\": \"{}\"}}",
+            writer::literal(value)
+        ),
+        Value::Boolean(v) => format!("{}", v),
+        Value::Null => String::from("null"),
+    }
+}
+
+fn typed(name: &str, value: &str, radix: Radix) -> String {
+    let radix = match radix {
+        Radix::Binary => ", \"radix\": 2",
+        Radix::Octal => ", \"radix\": 8",
+        Radix::Decimal => "",
+        Radix::Hexadecimal => ", \"radix\": 16",
+    };
+    format!("{{\"type\": \"{}\", \"value\": {}{}}}", name, value, radix)
+}
+
+/// Writes `text` as a JSON string.
+pub fn write_string(text: &str) -> String {
+    let mut out = String::from("\"");
+
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+// A JSON value and the line it starts on.
+struct Json {
+    kind: Kind,
+    line: usize,
+}
+
+enum Kind {
+    Null,
+    Boolean(bool),
+    // As written, so that integers and floats can be told apart.
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    // In source order.
+    Object(Vec<(String, Json)>),
+}
+
+fn read_tag(json: Json) -> Result<Tag, JsonError> {
+    let members = match json.kind {
+        Kind::Object(members) => members,
+        _ => return Err(JsonError::new("expected a tag object", json.line)),
+    };
+    let mut tag = Tag::new(String::new());
+
+    for (name, member) in members {
+        let line = member.line;
+        match (name.as_str(), member.kind) {
+            ("name", Kind::String(name)) => tag.set_name(name),
+            ("values", Kind::Array(values)) => {
+                for value in values {
+                    tag.push_value(read_value(value)?);
+                }
+            }
+            ("attributes", Kind::Object(attributes)) => {
+                for (name, value) in attributes {
+                    tag.set_attribute(name, read_value(value)?);
+                }
+            }
+            ("children", Kind::Array(children)) => {
+                for child in children {
+                    tag.add_child(read_tag(child)?);
+                }
+            }
+            ("name" | "values" | "attributes" | "children", _) => {
+                return Err(JsonError::new(
+                    format!("`{}` has the wrong type", name),
+                    line,
+                ))
+            }
+            _ => {
+                return Err(JsonError::new(
+                    format!("unknown tag member `{}`", name),
+                    line,
+                ))
+            }
+        }
+    }
+
+    Ok(tag)
+}
+
+fn read_friendly_tags(members: Vec<(String, Json)>) -> Result<Vec<Tag>, JsonError> {
+    let mut tags = Vec::new();
+
+    for (name, json) in members {
+        match json.kind {
+            Kind::Array(items) => {
+                for item in items {
+                    tags.push(read_friendly_tag(&name, item)?);
+                }
+            }
+            _ => tags.push(read_friendly_tag(&name, json)?),
+        }
+    }
+
+    Ok(tags)
+}
+
+fn read_friendly_tag(name: &str, json: Json) -> Result<Tag, JsonError> {
+    let mut tag = Tag::new(String::from(name));
+
+    let members = match json.kind {
+        Kind::Object(members) if !is_typed(&members) => members,
+        Kind::Array(_) => return Err(JsonError::new("expected a tag or a value", json.line)),
+        _ => {
+            tag.push_value(read_value(json)?);
+            return Ok(tag);
+        }
+    };
+
+    let mut children = Vec::new();
+    for (name, member) in members {
+        if name == "$values" {
+            match member.kind {
+                Kind::Array(values) => {
+                    for value in values {
+                        tag.push_value(read_value(value)?);
+                    }
+                }
+                _ => return Err(JsonError::new("`$values` has the wrong type", member.line)),
+            }
+        } else if let Some(attribute) = name.strip_prefix('@') {
+            tag.set_attribute(attribute, read_value(member)?);
+        } else {
+            children.push((name, member));
+        }
+    }
+    for child in read_friendly_tags(children)? {
+        tag.add_child(child);
+    }
+
+    Ok(tag)
+}
+
+// Whether an object is a value written as its type.
+fn is_typed(members: &[(String, Json)]) -> bool {
+    let member = |name: &str| members.iter().find(|(member, _)| member == name);
+
+    matches!(
+        member("type"),
+        Some((
+            _,
+            Json {
+                kind: Kind::String(_),
+                ..
+            }
+        ))
+    ) && member("value").is_some()
+        && members
+            .iter()
+            .all(|(name, _)| matches!(name.as_str(), "type" | "value" | "radix"))
+}
+
+fn read_value(json: Json) -> Result<Value, JsonError> {
+    let line = json.line;
+
+    match json.kind {
+        Kind::Null => Ok(Value::Null),
+        Kind::Boolean(v) => Ok(Value::Boolean(v)),
+        Kind::String(v) => Ok(Value::String(v)),
+        Kind::Number(text) => number(&text, line),
+        Kind::Object(members) if is_typed(&members) => read_typed(members, line),
+        _ => Err(JsonError::new("expected a value", line)),
+    }
+}
+
+fn number(text: &str, line: usize) -> Result<Value, JsonError> {
+    if text.contains(['.', 'e', 'E']) {
+        return text
+            .parse()
+            .map(Value::Float)
+            .map_err(|_| JsonError::new("invalid number", line));
+    }
+
+    if let Ok(int) = text.parse() {
+        return Ok(Value::Integer(int, Radix::Decimal));
+    }
+    text.parse()
+        .map(|long| Value::Long(long, Radix::Decimal))
+        .map_err(|_| JsonError::new("number out of range", line))
+}
+
+fn read_typed(members: Vec<(String, Json)>, line: usize) -> Result<Value, JsonError> {
+    let mut kind = String::new();
+    let mut value = None;
+    let mut radix = Radix::Decimal;
+
+    for (name, member) in members {
+        match (name.as_str(), member.kind) {
+            ("type", Kind::String(name)) => kind = name,
+            ("value", v) => value = Some(v),
+            ("radix", Kind::Number(base)) => {
+                radix = match base.as_str() {
+                    "2" => Radix::Binary,
+                    "8" => Radix::Octal,
+                    "10" => Radix::Decimal,
+                    "16" => Radix::Hexadecimal,
+                    _ => return Err(JsonError::new(format!("unknown radix {}", base), line)),
+                }
+            }
+            _ => return Err(JsonError::new("`radix` has the wrong type", line)),
+        }
+    }
+
+    let out_of_range = || JsonError::new("number out of range", line);
+    match (kind.as_str(), value) {
+        ("integer", Some(Kind::Number(text))) => text
+            .parse()
+            .map(|v| Value::Integer(v, radix))
+            .map_err(|_| out_of_range()),
+        ("long", Some(Kind::Number(text))) => text
+            .parse()
+            .map(|v| Value::Long(v, radix))
+            .map_err(|_| out_of_range()),
+        ("float", Some(Kind::Number(text))) => text
+            .parse()
+            .map(Value::Float)
+            .map_err(|_| JsonError::new("invalid number", line)),
+        ("float", Some(Kind::String(text))) => match text.as_str() {
+            "nan" => Ok(Value::Float(f64::NAN)),
+            "inf" => Ok(Value::Float(f64::INFINITY)),
+            "-inf" => Ok(Value::Float(f64::NEG_INFINITY)),
+            _ => Err(JsonError::new(format!("unknown float `{}`", text), line)),
+        },
+        ("integer" | "long" | "float", _) => {
+            Err(JsonError::new(format!("invalid {} value", kind), line))
+        }
+        _ => Err(JsonError::new(
+            format!("unknown value type `{}`", kind),
+            line,
+        )),
+    }
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    position: usize,
+    line: usize,
+}
+
+// An array or object being read, with the line it starts on.
+enum Open {
+    Array(Vec<Json>, usize),
+    // With the name of the member being read.
+    Object(Vec<(String, Json)>, String, usize),
+}
+
+impl<'a> Reader<'a> {
+    // Open arrays and objects are kept on an explicit stack, like the tags
+    // of `Parser`.
+    fn document(&mut self) -> Result<Json, JsonError> {
+        let mut stack: Vec<Open> = Vec::new();
+
+        loop {
+            if stack.len() == MAX_DEPTH {
+                return Err(self.error("JSON is nested too deeply"));
+            }
+
+            self.whitespace();
+            let line = self.line;
+            let mut json = match self.peek() {
+                Some('[') => {
+                    self.advance();
+                    self.whitespace();
+                    if !self.eat(']') {
+                        stack.push(Open::Array(Vec::new(), line));
+                        continue;
+                    }
+                    Json {
+                        kind: Kind::Array(Vec::new()),
+                        line,
+                    }
+                }
+                Some('{') => {
+                    self.advance();
+                    self.whitespace();
+                    if !self.eat('}') {
+                        let name = self.member_name()?;
+                        stack.push(Open::Object(Vec::new(), name, line));
+                        continue;
+                    }
+                    Json {
+                        kind: Kind::Object(Vec::new()),
+                        line,
+                    }
+                }
+                _ => self.scalar()?,
+            };
+
+            // Adds the value to the innermost open container, and closes the
+            // containers that end after it.
+            loop {
+                self.whitespace();
+
+                match stack.last_mut() {
+                    None => {
+                        if self.position < self.text.len() {
+                            return Err(self.error("unexpected text after the document"));
+                        }
+                        return Ok(json);
+                    }
+                    Some(Open::Array(items, _)) => {
+                        items.push(json);
+                        if self.eat(',') {
+                            break;
+                        }
+                        if !self.eat(']') {
+                            return Err(self.error("expected ',' or ']' in an array"));
+                        }
+                    }
+                    Some(Open::Object(members, name, _)) => {
+                        members.push((std::mem::take(name), json));
+                        if self.eat(',') {
+                            self.whitespace();
+                            *name = self.member_name()?;
+                            break;
+                        }
+                        if !self.eat('}') {
+                            return Err(self.error("expected ',' or '}' in an object"));
+                        }
+                    }
+                }
+
+                json = match stack.pop() {
+                    Some(Open::Array(items, line)) => Json {
+                        kind: Kind::Array(items),
+                        line,
+                    },
+                    Some(Open::Object(members, _, line)) => Json {
+                        kind: Kind::Object(members),
+                        line,
+                    },
+                    None => unreachable!(),
+                };
+            }
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Json, JsonError> {
+        let line = self.line;
+
+        let kind = match self.peek() {
+            Some('"') => Kind::String(self.string()?),
+            Some('t') => self.keyword("true", Kind::Boolean(true))?,
+            Some('f') => self.keyword("false", Kind::Boolean(false))?,
+            Some('n') => self.keyword("null", Kind::Null)?,
+            Some('-' | '0'..='9') => self.number()?,
+            Some(_) => return Err(self.error("expected a JSON value")),
+            None => return Err(self.error("unexpected end of JSON")),
+        };
+
+        Ok(Json { kind, line })
+    }
+
+    // Reads a member name and the ':' after it.
+    fn member_name(&mut self) -> Result<String, JsonError> {
+        if self.peek() != Some('"') {
+            return Err(self.error("expected a member name"));
+        }
+        let name = self.string()?;
+
+        self.whitespace();
+        if !self.eat(':') {
+            return Err(self.error("expected ':' after a member name"));
+        }
+        Ok(name)
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.advance();
+        let mut out = String::new();
+
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(out),
+                Some('\\') => {
+                    let ch = match self.advance() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape in a string")),
+                    };
+                    out.push(ch);
+                }
+                Some(ch) if (ch as u32) < 0x20 => {
+                    return Err(self.error("control character in a string"))
+                }
+                Some(ch) => out.push(ch),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    // Reads the digits of a `\u` escape, and of the low surrogate that
+    // follows a high one.
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex()?;
+
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !(self.eat('\\') && self.eat('u')) {
+                return Err(self.error("unpaired surrogate in a string"));
+            }
+            let low = self.hex()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate in a string"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+
+        char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate in a string"))
+    }
+
+    fn hex(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .text
+            .get(self.position..self.position + 4)
+            .filter(|digits| digits.chars().all(|ch| ch.is_ascii_hexdigit()));
+
+        match digits.and_then(|digits| u32::from_str_radix(digits, 16).ok()) {
+            Some(code) => {
+                self.position += 4;
+                Ok(code)
+            }
+            None => Err(self.error("expected four hex digits after \\u")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Kind, JsonError> {
+        let start = self.position;
+
+        self.eat('-');
+        if !self.eat('0') && !self.digits() {
+            return Err(self.error("expected digits in a number"));
+        }
+        if self.eat('.') && !self.digits() {
+            return Err(self.error("expected digits after '.'"));
+        }
+        if self.eat('e') || self.eat('E') {
+            if !self.eat('+') {
+                self.eat('-');
+            }
+            if !self.digits() {
+                return Err(self.error("expected digits in an exponent"));
+            }
+        }
+
+        Ok(Kind::Number(String::from(&self.text[start..self.position])))
+    }
+
+    fn digits(&mut self) -> bool {
+        let start = self.position;
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.advance();
+        }
+        self.position > start
+    }
+
+    fn keyword(&mut self, keyword: &str, kind: Kind) -> Result<Kind, JsonError> {
+        if self.text[self.position..].starts_with(keyword) {
+            self.position += keyword.len();
+            Ok(kind)
+        } else {
+            Err(self.error("expected a JSON value"))
+        }
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+            self.advance();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.position += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+        }
+        Some(ch)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let matches = self.peek() == Some(expected);
+        if matches {
+            self.advance();
+        }
+        matches
+    }
+
+    fn error(&self, message: &str) -> JsonError {
+        JsonError::new(message, self.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::{IntegerOverflow, ParserOptions};
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn to_json() {
+        let tags = Parser::from_str("a \"x\ty\" 1 2.5 true null k=false {\n  b;\n}\nc;")
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            super::to_json(&tags, Mapping::Canonical),
+            r#"[
+  {
+    "name": "a",
+    "values": ["x\ty", 1, 2.5, true, null],
+    "attributes": {"k": false},
+    "children": [
+      {
+        "name": "b"
+      }
+    ]
+  },
+  {
+    "name": "c"
+  }
+]
+"#
+        );
+        assert_eq!(super::to_json(&[], Mapping::Canonical), "[]\n");
+    }
+
+    #[test]
+    fn typed_values() {
+        let options = ParserOptions {
+            extensions: true,
+            float_keywords: true,
+            integer_overflow: IntegerOverflow::Widen,
+            ..ParserOptions::default()
+        };
+        let tags = Parser::with_options(Scanner::new("a 0x1F 0b1 3000000000 nan 1.0;"), options)
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            super::to_json(&tags, Mapping::Canonical),
+            concat!(
+                "[\n  {\n    \"name\": \"a\",\n    \"values\": [",
+                "{\"type\": \"integer\", \"value\": 31, \"radix\": 16}, ",
+                "{\"type\": \"integer\", \"value\": 1, \"radix\": 2}, ",
+                "{\"type\": \"long\", \"value\": 3000000000}, ",
+                "{\"type\": \"float\", \"value\": \"nan\"}, 1.0]\n  }\n]\n"
+            )
+        );
+        assert_eq!(
+            super::to_json(&[Tag::new(String::new())], Mapping::Canonical),
+            "[\n  {}\n]\n"
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let tags = vec![
+            Tag::build("a")
+                .value("x\"\\\n\u{1}é")
+                .value(Value::Integer(-7, Radix::Octal))
+                .value(Value::Long(5, Radix::Decimal))
+                .value(Value::Long(i64::MIN, Radix::Hexadecimal))
+                .value(1e300)
+                .value(-0.0)
+                .value(f64::NEG_INFINITY)
+                .value(Value::Null)
+                .attr("k", false)
+                .child(Tag::build("b").attr("n", 3))
+                .build(),
+            Tag::new(String::new()),
+            Tag::build("").value(1).build(),
+        ];
+
+        for mapping in [Mapping::Canonical, Mapping::Friendly] {
+            let json = super::to_json(&tags, mapping);
+            assert_eq!(from_json(&json, mapping).unwrap(), tags, "{}", json);
+        }
+
+        let json = r#"[{"values": [{"type": "float", "value": "nan"}]}]"#;
+        let nan = from_json(json, Mapping::Canonical).unwrap();
+        assert!(matches!(nan[0].values(), [Value::Float(v)] if v.is_nan()));
+    }
+
+    #[test]
+    fn config() {
+        let tags = Parser::from_str(include_str!("../config.sdl"))
+            .parse()
+            .unwrap();
+        let json = super::to_json(&tags, Mapping::Canonical);
+
+        assert_eq!(from_json(&json, Mapping::Canonical).unwrap(), tags);
+    }
+
+    #[test]
+    fn friendly() {
+        let tags = Parser::from_str(concat!(
+            "name \"x\";\nversion \"1.0\";\nfiles \"a\" \"b\";\nprivate;\n",
+            "scripts {\n  build \"make\";\n  format \"prettier\" check=true;\n}\n",
+            "keyword \"a\";\nkeyword \"b\";"
+        ))
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            super::to_json(&tags, Mapping::Friendly),
+            r#"{
+  "name": "x",
+  "version": "1.0",
+  "files": {
+    "$values": ["a", "b"]
+  },
+  "private": {},
+  "scripts": {
+    "build": "make",
+    "format": {
+      "$values": ["prettier"],
+      "@check": true
+    }
+  },
+  "keyword": [
+    "a",
+    "b"
+  ]
+}
+"#
+        );
+        assert_eq!(super::to_json(&[], Mapping::Friendly), "{}\n");
+
+        let json =
+            r#"{"name": "x", "n": {"type": "long", "value": 1}, "deps": {"a": "^1", "b": null}}"#;
+        assert_eq!(
+            from_json(json, Mapping::Friendly).unwrap(),
+            vec![
+                Tag::build("name").value("x").build(),
+                Tag::build("n")
+                    .value(Value::Long(1, Radix::Decimal))
+                    .build(),
+                Tag::build("deps")
+                    .child(Tag::build("a").value("^1"))
+                    .child(Tag::build("b").value(Value::Null))
+                    .build(),
+            ]
+        );
+    }
+
+    #[test]
+    fn deep_documents() {
+        let nested = |depth: usize| {
+            let mut tag = Tag::new(String::from("a"));
+            for _ in 1..depth {
+                tag = Tag::build("a").child(tag).build();
+            }
+            vec![tag]
+        };
+
+        let tags = nested(250);
+        let json = super::to_json(&tags, Mapping::Canonical);
+        assert_eq!(from_json(&json, Mapping::Canonical).unwrap(), tags);
+
+        let json = super::to_json(&nested(300), Mapping::Canonical);
+        assert!(from_json(&json, Mapping::Canonical).is_err());
+
+        // Indentation makes the JSON quadratic in the depth, so a small
+        // stack stands in for a deeper tree.
+        let deep = nested(2_000);
+        let (canonical, friendly) = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || {
+                (
+                    super::to_json(&deep, Mapping::Canonical),
+                    super::to_json(&deep, Mapping::Friendly),
+                )
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(canonical.ends_with("}\n]\n"));
+        assert!(friendly.ends_with("  }\n}\n"));
+    }
+
+    #[test]
+    fn plain_numbers() {
+        let json = r#"[{"values": [1, -2, 3000000000, 1.5, 2e3, -0]}]"#;
+
+        assert_eq!(
+            from_json(json, Mapping::Canonical).unwrap()[0].values(),
+            &[
+                Value::from(1),
+                Value::Integer(-2, Radix::Decimal),
+                Value::Long(3_000_000_000, Radix::Decimal),
+                Value::Float(1.5),
+                Value::Float(2000.0),
+                Value::from(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error =
+            |json: &str, mapping: Mapping| from_json(json, mapping).unwrap_err().to_string();
+
+        assert_eq!(
+            error("[\n  {\"nam\": \"a\"}\n]", Mapping::Canonical),
+            "unknown tag member `nam` at line 2"
+        );
+        assert_eq!(
+            error("{}", Mapping::Canonical),
+            "expected an array of tags at line 1"
+        );
+        assert_eq!(
+            error("[]", Mapping::Friendly),
+            "expected an object at line 1"
+        );
+        assert_eq!(
+            error("[{\"values\": [[1]]}]", Mapping::Canonical),
+            "expected a value at line 1"
+        );
+        assert_eq!(
+            error(
+                "[{\"values\": [{\"type\": \"date\", \"value\": 1}]}]",
+                Mapping::Canonical
+            ),
+            "unknown value type `date` at line 1"
+        );
+        assert_eq!(
+            error(
+                "[{\"values\": [{\"type\": \"integer\", \"value\": 3000000000}]}]",
+                Mapping::Canonical
+            ),
+            "number out of range at line 1"
+        );
+        assert_eq!(
+            error("[\n\n{\"a\": 01}]", Mapping::Canonical),
+            "expected ',' or '}' in an object at line 3"
+        );
+        assert_eq!(
+            error("[\"\\ud800\"]", Mapping::Canonical),
+            "unpaired surrogate in a string at line 1"
+        );
+        assert_eq!(
+            error("[] x", Mapping::Canonical),
+            "unexpected text after the document at line 1"
+        );
+        assert_eq!(
+            error(&"[".repeat(2000), Mapping::Canonical),
+            "JSON is nested too deeply at line 1"
+        );
+    }
+}
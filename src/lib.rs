@@ -0,0 +1,15 @@
+pub mod builder;
+pub mod convert;
+pub mod edit;
+pub mod format;
+pub mod json;
+pub mod options;
+pub mod parser;
+pub mod pull;
+pub mod scanner;
+pub mod source;
+pub mod visit;
+pub mod writer;
+
+pub use convert::{FromSdl, ToSdl};
+pub use rust_sdl_derive::{FromSdl, ToSdl};
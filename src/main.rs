@@ -1,23 +1,503 @@
-mod parser;
-mod scanner;
+use rust_sdl::edit::{self, EditError};
+use rust_sdl::format::{format, Style};
+use rust_sdl::json;
+use rust_sdl::options::{IntegerOverflow, ParserOptions};
+use rust_sdl::parser::{Error, Parser, Tag, Value, Warning};
+use rust_sdl::scanner::Scanner;
+use rust_sdl::visit;
+use rust_sdl::writer;
+use std::io::{self, Read};
+use std::process::ExitCode;
 
-use parser::Parser;
-use scanner::Scanner;
+const USAGE: &str = "usage: sdl <command> [options]
 
-fn main() -> std::io::Result<()> {
-    let mut cwd = std::env::current_dir().unwrap();
+commands:
+  check [--json] FILES...          report syntax errors and warnings
+  fmt [--check] [--json] FILES...  format files in place, or print the
+                                   files that are not formatted
+  get [--json] PATH FILE           print the values at PATH, like
+                                   `scripts/build/prod`, or an attribute
+                                   with `server@port`
+  convert [--from json|sdl] --to json|sdl [--friendly] FILE
+                                   print FILE as JSON or canonical SDL,
+                                   reading it as JSON if it ends in .json,
+                                   with the friendly JSON mapping
+  set [--dry-run] PATH FILE VALUE...
+                                   replace the values at PATH, or set an
+                                   attribute with `server@port`
+  add [--dry-run] PATH FILE TAGS   add TAGS, like `test \"jest\";`, as
+                                   children of PATH, or at the end of the
+                                   file for `/`
+  delete [--dry-run] PATH FILE     remove the tags, or the attribute, at PATH
 
-    cwd.push("config.sdl");
+A FILE of `-` is read from standard input. With --json, results are
+printed as one JSON value per line. set, add and delete only change FILE
+where it is edited, and with --dry-run print a diff instead. VALUEs are
+SDL literals, and anything else is taken as a string.";
 
-    let source = std::fs::read_to_string(cwd)?;
-    let ref mut scanner = Scanner::new(&source);
+// A failure that stops the command, reported with exit code 2.
+type Fatal = String;
 
-    let parser = Parser::new(scanner);
-    let tags = parser.parse();
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-    for tag in tags {
-        println!("{:#?}", tag);
+    let result = match args.split_first() {
+        Some((command, args)) => match command.as_str() {
+            "check" => check(args),
+            "fmt" => fmt(args),
+            "get" => get(args),
+            "convert" => convert(args),
+            "set" => set(args),
+            "add" => add(args),
+            "delete" => delete(args),
+            "help" | "-h" | "--help" => {
+                println!("{}", USAGE);
+                Ok(true)
+            }
+            _ => Err(format!("unknown command `{}`\n\n{}", command, USAGE)),
+        },
+        None => Err(String::from(USAGE)),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(message) => {
+            eprintln!("sdl: {}", message);
+            ExitCode::from(2)
+        }
+    }
+}
+
+// Command-line arguments split into flags and the rest.
+struct Args<'a> {
+    flags: Vec<&'a str>,
+    options: Vec<(&'a str, &'a str)>,
+    operands: Vec<&'a str>,
+}
+
+// `options` names the flags that take a value.
+fn parse_args<'a>(args: &'a [String], flags: &[&str], options: &[&str]) -> Result<Args<'a>, Fatal> {
+    let mut parsed = Args {
+        flags: Vec::new(),
+        options: Vec::new(),
+        operands: Vec::new(),
+    };
+    let mut args = args.iter().map(String::as_str);
+
+    while let Some(arg) = args.next() {
+        if flags.contains(&arg) {
+            parsed.flags.push(arg);
+        } else if options.contains(&arg) {
+            match args.next() {
+                Some(value) => parsed.options.push((arg, value)),
+                None => return Err(format!("{} needs a value", arg)),
+            }
+        } else if arg.starts_with("--") {
+            return Err(format!("unknown option `{}`", arg));
+        } else {
+            parsed.operands.push(arg);
+        }
+    }
+
+    Ok(parsed)
+}
+
+impl<'a> Args<'a> {
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(&name)
+    }
+
+    fn option(&self, name: &str) -> Option<&'a str> {
+        self.options
+            .iter()
+            .find(|(option, _)| *option == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+fn read(path: &str) -> Result<String, Fatal> {
+    let result = if path == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source).map(|_| source)
+    } else {
+        std::fs::read_to_string(path)
+    };
+
+    result.map_err(|error| format!("{}: {}", path, error))
+}
+
+fn parse(path: &str, source: &str) -> Result<Vec<Tag>, Fatal> {
+    Parser::from_str(source)
+        .parse()
+        .map_err(|error| format!("{}: {}", path, error.report(source)))
+}
+
+fn check(args: &[String]) -> Result<bool, Fatal> {
+    let args = parse_args(args, &["--json"], &[])?;
+    let machine = args.flag("--json");
+    let mut ok = true;
+    let mut unreadable = 0;
+
+    if args.operands.is_empty() {
+        return Err(String::from("check needs at least one FILE"));
+    }
+
+    // A file that cannot be read does not stop the others from being
+    // checked.
+    for path in &args.operands {
+        let source = match read(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("sdl: {}", error);
+                unreadable += 1;
+                continue;
+            }
+        };
+        let mut parser = Parser::from_str(&source);
+        let result = parser.parse();
+
+        if let Err(error) = result {
+            ok = false;
+            if machine {
+                let Error(message, start, end, line, _) = error;
+                println!(
+                    "{}",
+                    diagnostic(path, Severity::Error, message, line, start, end)
+                );
+            } else {
+                eprint!("{}: {}", path, error.report(&source));
+            }
+        }
+
+        for warning in parser.warnings() {
+            if machine {
+                let Warning(message, start, end, line) = *warning;
+                println!(
+                    "{}",
+                    diagnostic(path, Severity::Warning, message, line, start, end)
+                );
+            } else {
+                eprint!("{}: {}", path, warning.report(&source));
+            }
+        }
+    }
+
+    if unreadable > 0 {
+        return Err(format!(
+            "{} of {} files could not be read",
+            unreadable,
+            args.operands.len()
+        ));
+    }
+    Ok(ok)
+}
+
+enum Severity {
+    Error,
+    Warning,
+}
+
+fn diagnostic(
+    path: &str,
+    severity: Severity,
+    message: &str,
+    line: usize,
+    start: usize,
+    end: usize,
+) -> String {
+    let severity = match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    format!(
+        "{{\"file\": {}, \"severity\": \"{}\", \"line\": {}, \"start\": {}, \"end\": {}, \"message\": {}}}",
+        json::write_string(path),
+        severity,
+        line,
+        start,
+        end,
+        json::write_string(message)
+    )
+}
+
+fn fmt(args: &[String]) -> Result<bool, Fatal> {
+    let args = parse_args(args, &["--check", "--json"], &[])?;
+    let machine = args.flag("--json");
+    let mut ok = true;
+
+    if args.operands.is_empty() {
+        return Err(String::from("fmt needs at least one FILE"));
+    }
+
+    for path in &args.operands {
+        let source = read(path)?;
+        let formatted = format(&source, ParserOptions::default(), &Style::default())
+            .map_err(|error| format!("{}: {}", path, error.report(&source)))?;
+        let changed = formatted != source;
+
+        if args.flag("--check") {
+            if machine {
+                println!(
+                    "{{\"file\": {}, \"formatted\": {}}}",
+                    json::write_string(path),
+                    !changed
+                );
+            } else if changed {
+                println!("{}", path);
+            }
+            ok &= !changed;
+        } else if *path == "-" {
+            print!("{}", formatted);
+        } else if changed {
+            std::fs::write(path, formatted).map_err(|error| format!("{}: {}", path, error))?;
+        }
+    }
+
+    Ok(ok)
+}
+
+fn get(args: &[String]) -> Result<bool, Fatal> {
+    let args = parse_args(args, &["--json"], &[])?;
+    let (path, file) = match args.operands.as_slice() {
+        [path, file] => (*path, *file),
+        _ => return Err(String::from("get needs a PATH and a FILE")),
+    };
+
+    let source = read(file)?;
+    let tags = parse(file, &source)?;
+    let (tag_path, attribute) = match path.split_once('@') {
+        Some((tag_path, attribute)) => (tag_path, Some(attribute)),
+        None => (path, None),
+    };
+
+    // One line per tag found, of its values or of the attribute.
+    let lines: Vec<Vec<&Value>> = visit::find(&tags, tag_path)
+        .into_iter()
+        .filter_map(|tag| match attribute {
+            Some(name) => tag.attribute(name).map(|value| vec![value]),
+            None => Some(tag.values().iter().collect()),
+        })
+        .collect();
+
+    for values in &lines {
+        if args.flag("--json") {
+            let values: Vec<String> = values.iter().map(|v| json::write_value(v)).collect();
+            println!("[{}]", values.join(", "));
+        } else {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            println!("{}", values.join(" "));
+        }
+    }
+
+    if lines.is_empty() {
+        eprintln!("sdl: nothing found at `{}`", path);
+    }
+    Ok(!lines.is_empty())
+}
+
+fn convert(args: &[String]) -> Result<bool, Fatal> {
+    let args = parse_args(args, &["--friendly"], &["--from", "--to"])?;
+    let file = match args.operands.as_slice() {
+        [file] => *file,
+        _ => return Err(String::from("convert needs a FILE")),
+    };
+    let mapping = if args.flag("--friendly") {
+        json::Mapping::Friendly
+    } else {
+        json::Mapping::Canonical
+    };
+
+    let from = match args.option("--from") {
+        Some(from) => from,
+        None if file.ends_with(".json") => "json",
+        None => "sdl",
+    };
+    let source = read(file)?;
+    let tags = match from {
+        "sdl" => parse(file, &source)?,
+        "json" => {
+            json::from_json(&source, mapping).map_err(|error| format!("{}: {}", file, error))?
+        }
+        other => return Err(format!("cannot convert from `{}`", other)),
+    };
+
+    match args.option("--to") {
+        Some("json") => print!("{}", json::to_json(&tags, mapping)),
+        Some("sdl") => {
+            let written = writer::write(&tags).map_err(|error| format!("{}: {}", file, error))?;
+            print!("{}", written);
+        }
+        Some(other) => return Err(format!("cannot convert to `{}`", other)),
+        None => return Err(String::from("convert needs --to json or --to sdl")),
+    }
+
+    Ok(true)
+}
+
+fn set(args: &[String]) -> Result<bool, Fatal> {
+    let args = parse_args(args, &["--dry-run"], &[])?;
+    let (path, file, values) = match args.operands.as_slice() {
+        [path, file, values @ ..] => (*path, *file, values),
+        _ => return Err(String::from("set needs a PATH, a FILE and VALUEs")),
+    };
+    let values = values
+        .iter()
+        .map(|value| literal(value))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let source = read(file)?;
+    let result = match (path.split_once('@'), values.as_slice()) {
+        (Some((path, name)), [value]) => edit::set_attribute(&source, path, name, value),
+        (Some(_), _) => return Err(String::from("an attribute takes a single VALUE")),
+        (None, values) => edit::set_values(&source, path, values),
+    };
+
+    write_edit(&args, file, &source, result)
+}
+
+fn add(args: &[String]) -> Result<bool, Fatal> {
+    let args = parse_args(args, &["--dry-run"], &[])?;
+    let (path, file, children) = match args.operands.as_slice() {
+        [path, file, children] => (*path, *file, *children),
+        _ => return Err(String::from("add needs a PATH, a FILE and TAGS")),
+    };
+    let children = parse("TAGS", children)?;
+
+    let source = read(file)?;
+    let mut result = Ok(source.clone());
+    for child in &children {
+        result = result.and_then(|edited| edit::add_child(&edited, path, child));
+    }
+
+    write_edit(&args, file, &source, result)
+}
+
+fn delete(args: &[String]) -> Result<bool, Fatal> {
+    let args = parse_args(args, &["--dry-run"], &[])?;
+    let (path, file) = match args.operands.as_slice() {
+        [path, file] => (*path, *file),
+        _ => return Err(String::from("delete needs a PATH and a FILE")),
+    };
+
+    let source = read(file)?;
+    let result = match path.split_once('@') {
+        Some((path, name)) => edit::remove_attribute(&source, path, name),
+        None => edit::delete(&source, path),
+    };
+
+    write_edit(&args, file, &source, result)
+}
+
+// Reads a VALUE given to `set`: an SDL literal, or else a string. Integers
+// too large for 32 bits are longs.
+fn literal(text: &str) -> Result<Value, Fatal> {
+    let source = format!("_ {};", text);
+    let options = ParserOptions {
+        integer_overflow: IntegerOverflow::Widen,
+        ..ParserOptions::default()
+    };
+    let parsed = Parser::with_options(Scanner::new(&source), options).parse();
+
+    if let Err(Error("Integer literal out of range.", ..)) = parsed {
+        return Err(format!("`{}` is out of range", text));
+    }
+    if let Ok(tags) = parsed {
+        if let [tag] = tags.as_slice() {
+            if let ([value], true) = (tag.values(), tag.attributes().is_empty()) {
+                return Ok(value.clone());
+            }
+        }
+    }
+
+    if text.contains('"') {
+        return Err(format!(
+            "`{}` is not a literal and cannot be a string",
+            text
+        ));
+    }
+    Ok(Value::from(text))
+}
+
+// Writes the edited source back to `file`, or to standard output for `-`,
+// or prints how it changed with --dry-run.
+fn write_edit(
+    args: &Args,
+    file: &str,
+    source: &str,
+    result: Result<String, EditError>,
+) -> Result<bool, Fatal> {
+    let edited = match result {
+        Ok(edited) => edited,
+        Err(EditError::Syntax(error)) => return Err(format!("{}: {}", file, error.report(source))),
+        Err(error) => {
+            eprintln!("sdl: {}", error);
+            return Ok(false);
+        }
+    };
+
+    if args.flag("--dry-run") {
+        print!("{}", diff(file, source, &edited));
+    } else if file == "-" {
+        print!("{}", edited);
+    } else if edited != source {
+        std::fs::write(file, edited).map_err(|error| format!("{}: {}", file, error))?;
+    }
+
+    Ok(true)
+}
+
+// A unified diff of `old` and `new` with a single hunk, running from the
+// first changed line to the last with three lines of context.
+fn diff(file: &str, old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let old: Vec<&str> = old.split_inclusive('\n').collect();
+    let new: Vec<&str> = new.split_inclusive('\n').collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    if prefix == old.len() && prefix == new.len() {
+        return String::new();
+    }
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let start = prefix.saturating_sub(CONTEXT);
+    let old_end = (old.len() - suffix + CONTEXT).min(old.len());
+    let new_end = (new.len() - suffix + CONTEXT).min(new.len());
+
+    let mut out = format!("--- {}\n+++ {}\n", file, file);
+    out.push_str(&format!(
+        "@@ -{},{} +{},{} @@\n",
+        start + 1,
+        old_end - start,
+        start + 1,
+        new_end - start
+    ));
+
+    let mut line = |marker: char, text: &str| {
+        out.push(marker);
+        out.push_str(text);
+        if !text.ends_with('\n') {
+            out.push_str("\n\\ No newline at end of file\n");
+        }
+    };
+    for text in &old[start..prefix] {
+        line(' ', text);
+    }
+    for text in &old[prefix..old.len() - suffix] {
+        line('-', text);
+    }
+    for text in &new[prefix..new.len() - suffix] {
+        line('+', text);
+    }
+    for text in &old[old.len() - suffix..old_end] {
+        line(' ', text);
     }
 
-    Ok(())
+    out
 }
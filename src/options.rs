@@ -0,0 +1,106 @@
+/// Settings shared by `Parser`, `PullParser` and `Scanner`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParserOptions {
+    /// Reject values that follow an attribute in a tag. When unset, they are
+    /// accepted and each is reported as a warning. Other options, such as
+    /// `extensions`, are not affected.
+    pub strict: bool,
+    /// What to do when a tag repeats an attribute name.
+    pub duplicate_attributes: DuplicateAttributes,
+    /// Maximum number of nested tag bodies. Defaults to 256.
+    pub max_depth: Option<usize>,
+    /// Maximum length of the document in bytes.
+    pub max_size: Option<usize>,
+    /// Maximum number of tags in the document, at any depth.
+    pub max_tags: Option<usize>,
+    /// Maximum number of values of a single tag.
+    pub max_values: Option<usize>,
+    /// Maximum number of distinct attributes of a single tag.
+    pub max_attributes: Option<usize>,
+    /// Maximum length in bytes of the contents of a string literal.
+    pub max_string_length: Option<usize>,
+    /// Maximum number of warnings kept outside of strict mode. Once it is
+    /// reached, one last warning notes that the rest were suppressed.
+    /// Defaults to 100.
+    pub max_warnings: Option<usize>,
+    /// What to do with integer literals that do not fit in 32 bits.
+    pub integer_overflow: IntegerOverflow,
+    /// Accept tags without values or attributes, like `enabled;`. Such
+    /// tags are legal SDLang and commonly used as flags.
+    pub allow_empty_tags: bool,
+    /// End tags at the end of a line as well as at `;`, as SDLang does. A
+    /// `\` at the end of a line continues the tag on the next one.
+    pub newline_terminators: bool,
+    /// Comment styles recognised by the scanner.
+    pub comments: CommentStyles,
+    /// Read the keywords `nan` and `inf` as float values. Not part of
+    /// SDLang.
+    pub float_keywords: bool,
+    /// Accept hexadecimal (`0xFF`), octal (`0o755`) and binary (`0b1010`)
+    /// integers, and `_` between digits (`1_000_000`). Not part of SDLang.
+    pub extensions: bool,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            strict: false,
+            duplicate_attributes: DuplicateAttributes::default(),
+            max_depth: Some(256),
+            max_size: None,
+            max_tags: None,
+            max_values: None,
+            max_attributes: None,
+            max_string_length: None,
+            max_warnings: Some(100),
+            integer_overflow: IntegerOverflow::default(),
+            allow_empty_tags: true,
+            newline_terminators: false,
+            comments: CommentStyles::default(),
+            float_keywords: false,
+            extensions: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DuplicateAttributes {
+    /// The last value wins.
+    #[default]
+    Replace,
+    /// The first value wins; later ones are dropped.
+    KeepFirst,
+    /// A repeated attribute is a syntax error.
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum IntegerOverflow {
+    /// An out of range literal is a syntax error.
+    #[default]
+    Error,
+    /// Literals that fit in 64 bits become `Value::Long`.
+    Widen,
+    /// Literals are clamped to the 32 bit range.
+    Saturate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommentStyles {
+    /// `// comment`
+    pub double_slash: bool,
+    /// `# comment`
+    pub hash: bool,
+    /// `-- comment`
+    pub double_dash: bool,
+}
+
+impl Default for CommentStyles {
+    fn default() -> Self {
+        CommentStyles {
+            double_slash: true,
+            hash: true,
+            double_dash: true,
+        }
+    }
+}
@@ -1,12 +1,31 @@
+use crate::convert::{FromValue, Numeric, ValueError};
+use crate::options::ParserOptions;
+use crate::pull::{Event, PullParser};
 use crate::scanner::*;
-use std::cmp;
+use crate::source::{ReadSource, StrSource};
+use crate::writer;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::BufRead;
 
-#[derive(Debug)]
+/// A literal value.
+///
+/// Values are totally ordered, so they can be sorted and used as keys of
+/// sets and maps:
+///
+/// - `null` < booleans < integers < floats < strings.
+/// - `Integer` and `Long` compare by their numeric value; the radix they
+///   were written in is ignored, so `0x10` equals `16`.
+/// - Floats compare with `f64::total_cmp`: `-0.0` is less than `0.0`, and
+///   NaN equals itself and sorts after every other float (or before them
+///   when negative).
+#[derive(Debug, Clone)]
 pub enum Value {
     String(String),
-    Integer(i32),
+    Integer(i32, Radix),
+    Long(i64, Radix),
     Float(f64),
     Boolean(bool),
     Null,
@@ -16,7 +35,8 @@ impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Value::String(v) => write!(f, "{}", v),
-            Value::Integer(v) => write!(f, "{}", v),
+            Value::Integer(v, _) => write!(f, "{}", v),
+            Value::Long(v, _) => write!(f, "{}", v),
             Value::Float(v) => write!(f, "{}", v),
             Value::Null => write!(f, "null"),
             Value::Boolean(b) => write!(f, "{}", b),
@@ -24,47 +44,231 @@ impl fmt::Display for Value {
     }
 }
 
-#[derive(Debug)]
+impl Value {
+    /// The name of the value's type, for messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(..) | Value::Long(..) => "integer",
+            Value::Float(_) => "float",
+            Value::Boolean(_) => "boolean",
+            Value::Null => "null",
+        }
+    }
+
+    /// Converts the value to `T`, widening numbers as `numeric` allows.
+    /// `TryFrom` converts the same way with `Numeric::Lossless`.
+    pub fn get<T: FromValue>(&self, numeric: Numeric) -> Result<T, ValueError> {
+        T::from_value(self.clone(), numeric)
+    }
+
+    // Position of the variant in the order of values.
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Boolean(_) => 1,
+            Value::Integer(..) | Value::Long(..) => 2,
+            Value::Float(_) => 3,
+            Value::String(_) => 4,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+            (Value::Integer(a, _), Value::Integer(b, _)) => a.cmp(b),
+            (Value::Integer(a, _), Value::Long(b, _)) => i64::from(*a).cmp(b),
+            (Value::Long(a, _), Value::Integer(b, _)) => a.cmp(&i64::from(*b)),
+            (Value::Long(a, _), Value::Long(b, _)) => a.cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+
+        match self {
+            Value::Boolean(v) => v.hash(state),
+            Value::Integer(v, _) => i64::from(*v).hash(state),
+            Value::Long(v, _) => v.hash(state),
+            // Equal under `total_cmp` means equal bits.
+            Value::Float(v) => v.to_bits().hash(state),
+            Value::String(v) => v.hash(state),
+            Value::Null => (),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::String(String::from(v))
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::String(v)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(v: i32) -> Self {
+        Value::Integer(v, Radix::Decimal)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Long(v, Radix::Decimal)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Float(v)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Boolean(v)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Value::Null, Into::into)
+    }
+}
+
+/// The base an integer literal was written in, kept so that it can be
+/// written back the same way.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Radix {
+    Binary,
+    Octal,
+    #[default]
+    Decimal,
+    Hexadecimal,
+}
+
+impl Radix {
+    pub fn base(self) -> u32 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Decimal => 10,
+            Radix::Hexadecimal => 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Tag {
-    name: String,
-    values: Vec<Value>,
-    attributes: HashMap<String, Value>,
-    children: Vec<Tag>,
+    pub(crate) name: String,
+    pub(crate) values: Vec<Value>,
+    pub(crate) attributes: HashMap<String, Value>,
+    pub(crate) children: Vec<Tag>,
+    pub(crate) line: Option<usize>,
 }
 
+/// Tags are equal when their names, values, attributes and children are.
+/// Values and children are compared in order; attributes are compared by
+/// name, whatever order they were written in. The line a tag was read from
+/// is ignored.
+impl PartialEq for Tag {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.values == other.values
+            && self.attributes_eq(other)
+            && self.children == other.children
+    }
+}
+
+impl Eq for Tag {}
+
+/// A tree dump for people, with attributes in name order and values
+/// written as SDL literals. See `writer::write` for SDL output.
 impl fmt::Display for Tag {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut indent = 2;
-        write!(f, "Tag {} {{", self.name)?;
-        write!(f, "\n{:>w$}values: ", "", w = indent)?;
+        enum Step<'a> {
+            Open(&'a Tag, usize),
+            Close(usize),
+        }
 
-        // f.debug_list().entries(&self.values).finish()?;
+        let mut steps = vec![Step::Open(self, 0)];
 
-        for (i, value) in self.values.iter().enumerate() {
-            if i == self.values.len() - 1 {
-                write!(f, "{}", value)?;
-            } else {
-                write!(f, "{}, ", value)?;
+        while let Some(step) = steps.pop() {
+            let (tag, indent) = match step {
+                Step::Open(tag, indent) => (tag, indent),
+                Step::Close(indent) => {
+                    writeln!(f, "{:w$}}}", "", w = indent)?;
+                    continue;
+                }
+            };
+
+            if tag.values.is_empty() && tag.attributes.is_empty() && tag.children.is_empty() {
+                writeln!(f, "{:w$}Tag {} {{}}", "", tag.name, w = indent)?;
+                continue;
             }
-        }
 
-        if self.attributes.len() > 0 {
-            write!(f, "\n{:>w$}attributes: ", "", w = indent)?;
-            for attribute in self.attributes.iter() {
-                write!(f, "{}={}", attribute.0, attribute.1)?;
+            writeln!(f, "{:w$}Tag {} {{", "", tag.name, w = indent)?;
+
+            if !tag.values.is_empty() {
+                let values: Vec<_> = tag.values.iter().map(writer::literal).collect();
+                writeln!(f, "{:w$}values: {}", "", values.join(", "), w = indent + 2)?;
+            }
+
+            if !tag.attributes.is_empty() {
+                let mut attributes: Vec<_> = tag.attributes.iter().collect();
+                attributes.sort_by(|a, b| a.0.cmp(b.0));
+                let attributes: Vec<_> = attributes
+                    .into_iter()
+                    .map(|(name, value)| format!("{}={}", name, writer::literal(value)))
+                    .collect();
+                writeln!(
+                    f,
+                    "{:w$}attributes: {}",
+                    "",
+                    attributes.join(", "),
+                    w = indent + 2
+                )?;
             }
-        }
 
-        if self.children.len() > 0 {
-            write!(f, "\n{:>w$}children:\n", "", w = indent)?;
-            indent *= 2;
+            steps.push(Step::Close(indent));
 
-            for child in self.children.iter() {
-                write!(f, "{:>w$}{}", child, "", w = indent)?;
+            if !tag.children.is_empty() {
+                writeln!(f, "{:w$}children:", "", w = indent + 2)?;
+                steps.extend(
+                    tag.children
+                        .iter()
+                        .rev()
+                        .map(|child| Step::Open(child, indent + 4)),
+                );
             }
         }
 
-        write!(f, "\n}}\n")
+        Ok(())
     }
 }
 
@@ -75,248 +279,835 @@ impl Tag {
             values: Vec::new(),
             children: Vec::new(),
             attributes: HashMap::new(),
+            line: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The line the tag was read from, for tags built by a `Parser`.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = name.into();
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn values_mut(&mut self) -> &mut Vec<Value> {
+        &mut self.values
+    }
+
+    pub fn push_value(&mut self, value: Value) {
+        self.values.push(value);
+    }
+
+    pub fn attributes(&self) -> &HashMap<String, Value> {
+        &self.attributes
+    }
+
+    /// Whether both tags have the same attributes, in any order.
+    pub fn attributes_eq(&self, other: &Tag) -> bool {
+        self.attributes == other.attributes
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Value> {
+        self.attributes.get(name)
+    }
+
+    pub fn attribute_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.attributes.get_mut(name)
+    }
+
+    /// Sets an attribute, returning the value it replaced.
+    pub fn set_attribute(&mut self, name: impl Into<String>, value: Value) -> Option<Value> {
+        self.attributes.insert(name.into(), value)
+    }
+
+    pub fn remove_attribute(&mut self, name: &str) -> Option<Value> {
+        self.attributes.remove(name)
+    }
+
+    pub fn children(&self) -> &[Tag] {
+        &self.children
+    }
+
+    pub fn children_mut(&mut self) -> &mut Vec<Tag> {
+        &mut self.children
+    }
+
+    /// The first child called `name`.
+    pub fn child(&self, name: &str) -> Option<&Tag> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// The first child called `name`.
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut Tag> {
+        self.children.iter_mut().find(|child| child.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Tag> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Whether the tag has no values, attributes or children, like
+    /// `enabled;` or `enabled {}`.
+    pub fn is_flag(&self) -> bool {
+        self.values.is_empty() && self.attributes.is_empty() && self.children.is_empty()
+    }
+
+    /// Whether a child called `name` is present as a flag.
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.children_named(name).any(Tag::is_flag)
+    }
+
+    pub fn add_child(&mut self, child: Tag) {
+        self.children.push(child);
+    }
+
+    /// Removes every child called `name` and returns them in order.
+    pub fn remove_children_named(&mut self, name: &str) -> Vec<Tag> {
+        let (removed, kept) = std::mem::take(&mut self.children)
+            .into_iter()
+            .partition(|child| child.name == name);
+        self.children = kept;
+        removed
+    }
+
+    /// Keeps only the children for which `keep` returns true.
+    pub fn retain_children(&mut self, keep: impl FnMut(&Tag) -> bool) {
+        self.children.retain(keep);
+    }
+}
+
+// Dropping children one level at a time keeps deeply nested trees from
+// overflowing the stack.
+impl Drop for Tag {
+    fn drop(&mut self) {
+        let mut descendants = std::mem::take(&mut self.children);
+
+        while let Some(mut tag) = descendants.pop() {
+            descendants.append(&mut tag.children);
         }
     }
 }
 
-#[derive(Debug)]
-struct Error(&'static str, usize, usize, usize);
+#[derive(Debug, PartialEq)]
+pub struct Error(
+    pub &'static str,
+    pub usize,
+    pub usize,
+    pub usize,
+    pub ErrorKind,
+);
 
-pub struct Parser<'a> {
-    scanner: &'a mut Scanner<'a>,
-    previous: Token,
-    current: Token,
-    tags: Vec<Tag>,
+impl Error {
+    /// The class of the error, for callers that handle some errors
+    /// differently from others.
+    pub fn kind(&self) -> ErrorKind {
+        self.4
+    }
+
+    /// Renders the error with the offending line of `source`.
+    pub fn report(&self, source: &str) -> String {
+        let Error(msg, start, end, line, _) = *self;
+        report("Syntax error", msg, start, end, line, source)
+    }
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(scanner: &'a mut Scanner<'a>) -> Self {
-        let previous = Token::Eof(0, 0, 1);
-        let current = scanner.next().unwrap_or(Token::Eof(0, 1, 1));
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Syntax error at line {}: {}", self.3, self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The document is not valid SDLang.
+    Syntax,
+    /// The input could not be read or is not UTF-8.
+    Input,
+    /// The document exceeds one of the limits in `ParserOptions`.
+    Limit(Limit),
+}
+
+/// The `ParserOptions` limit that was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// `max_depth`
+    Depth,
+    /// `max_size`
+    Size,
+    /// `max_tags`
+    Tags,
+    /// `max_values`
+    Values,
+    /// `max_attributes`
+    Attributes,
+    /// `max_string_length`
+    StringLength,
+}
+
+/// A rule violation accepted outside of strict mode.
+#[derive(Debug, PartialEq)]
+pub struct Warning(pub &'static str, pub usize, pub usize, pub usize);
+
+impl Warning {
+    /// Renders the warning with the offending line of `source`.
+    pub fn report(&self, source: &str) -> String {
+        let Warning(msg, start, end, line) = *self;
+        report("Warning", msg, start, end, line, source)
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Warning at line {}: {}", self.3, self.0)
+    }
+}
+
+fn report(kind: &str, msg: &str, start: usize, end: usize, line: usize, source: &str) -> String {
+    let mut report = String::new();
+    let rctx = source[end..].split('\n').next().unwrap_or("");
+
+    report.push_str(format!("{} at line {}: {}\n", kind, line, msg).as_str());
+    report.push_str("   |\n");
+    report.push_str(format!("{}  | {}{}\n", line, &source[start..end], rctx).as_str());
+    report.push_str(format!("   |{:>w$}\n", "^", w = 2).as_str());
+    report
+}
+
+/// Builds a tree of `Tag`s from any `TokenSource`.
+///
+/// Open tags are kept on an explicit stack rather than the call stack, so
+/// nesting is limited only by `ParserOptions::max_depth`.
+pub struct Parser<T> {
+    events: PullParser<T>,
+}
+
+impl<'a> Parser<Scanner<StrSource<'a>>> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(source: &'a str) -> Self {
+        Parser::new(Scanner::new(source))
+    }
+}
+
+impl<R: BufRead> Parser<Scanner<ReadSource<R>>> {
+    pub fn from_reader(reader: R) -> Self {
+        Parser::new(Scanner::from_reader(reader))
+    }
+}
+
+impl<T: TokenSource> Parser<T> {
+    pub fn new(tokens: T) -> Self {
+        Parser::with_options(tokens, ParserOptions::default())
+    }
+
+    pub fn with_options(tokens: T, options: ParserOptions) -> Self {
         Parser {
-            scanner,
-            previous,
-            current,
-            tags: vec![],
+            events: PullParser::with_options(tokens, options),
         }
     }
 
-    fn identifier(&mut self) -> Result<Option<String>, Error> {
-        match self.current {
-            Token::Identifier(s, e, _) => {
-                self.advance();
-                Ok(Some(String::from(self.scanner.source_slice(s, e))))
-            }
-            Token::Error(msg, s, e, l) => Err(Error(msg, s, e, l)),
-            _ => Ok(None),
-        }
+    /// Rule violations accepted so far outside of strict mode.
+    pub fn warnings(&self) -> &[Warning] {
+        self.events.warnings()
     }
 
-    fn literal(&mut self) -> Result<Option<Value>, Error> {
-        match self.current {
-            Token::Integer(s, e, _) => {
-                self.advance();
-                let int = str::parse::<i32>(self.scanner.source_slice(s, e)).unwrap();
-                Ok(Some(Value::Integer(int)))
-            }
-            Token::String(s, e, _) => {
-                self.advance();
-                let string = self.scanner.source_slice(s, e);
-                Ok(Some(Value::String(String::from(string))))
-            }
-            Token::Float64(s, e, _) => {
-                self.advance();
-                let float = str::parse::<f64>(self.scanner.source_slice(s, e)).unwrap();
-                Ok(Some(Value::Float(float)))
-            }
-            Token::True(_, _, _) => {
-                self.advance();
-                Ok(Some(Value::Boolean(true)))
-            }
-            Token::False(_, _, _) => {
-                self.advance();
-                Ok(Some(Value::Boolean(false)))
-            }
-            Token::Null(_, _, _) => {
-                self.advance();
-                Ok(Some(Value::Null))
+    /// Parses the remaining tags. Once an error has been returned, further
+    /// calls return no tags.
+    pub fn parse(&mut self) -> Result<Vec<Tag>, Error> {
+        let mut tags = Vec::new();
+        let mut open: Vec<Tag> = Vec::new();
+
+        while let Some(event) = self.events.next() {
+            match event? {
+                Event::StartTag { name } => {
+                    let mut tag = Tag::new(name);
+                    tag.line = Some(self.events.position().2);
+                    open.push(tag);
+                }
+                Event::Value(value) => match open.last_mut() {
+                    Some(tag) => tag.values.push(value),
+                    None => return Err(self.outside_tag()),
+                },
+                Event::Attribute { name, value } => match open.last_mut() {
+                    Some(tag) => {
+                        tag.attributes.insert(name, value);
+                    }
+                    None => return Err(self.outside_tag()),
+                },
+                Event::StartChildren => (),
+                Event::EndTag => {
+                    if let Some(tag) = open.pop() {
+                        match open.last_mut() {
+                            Some(parent) => parent.children.push(tag),
+                            None => tags.push(tag),
+                        }
+                    }
+                }
             }
-            Token::Error(msg, s, e, l) => Err(Error(msg, s, e, l)),
-            _ => Ok(None),
         }
+
+        Ok(tags)
+    }
+
+    // `PullParser` only produces values and attributes inside a tag, so this
+    // is never expected to happen.
+    fn outside_tag(&self) -> Error {
+        let (start, end, line) = self.events.position();
+        Error(
+            "Value outside of a tag.",
+            start,
+            end,
+            line,
+            ErrorKind::Syntax,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::{DuplicateAttributes, IntegerOverflow};
+
+    fn parse(source: &str) -> Result<Vec<Tag>, Error> {
+        Parser::from_str(source).parse()
     }
 
-    fn attribute(&mut self) -> Result<Option<(String, Value)>, Error> {
-        let name = self.identifier()?;
+    #[test]
+    fn builds_nested_tags() {
+        let tags = parse(r#"a 1; b { c "x"; d { e true; } }"#).unwrap();
 
-        match name {
-            Some(n) => match self.current {
-                Token::Equal(s, e, l) => {
-                    self.advance();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].values, vec![Value::Integer(1, Radix::Decimal)]);
+        assert_eq!(tags[1].children.len(), 2);
+        assert_eq!(tags[1].children[1].children[0].name, "e");
+    }
 
-                    let literal = self.literal()?;
+    #[test]
+    fn tag_lines() {
+        let tags = parse("a;\n\nb {\n  c 1;\n}").unwrap();
 
-                    match literal {
-                        Some(value) => Ok(Some((n, value))),
-                        None => Err(Error("Expect literal after '='.", s, e, l)),
-                    }
-                }
-                Token::Eof(s, e, l) => {
-                    return Err(Error("Unexpected identifier.", s, e, l));
-                }
-                ref t @ _ => {
-                    let (start, end, line) = t.position();
-                    return Err(Error("Expect '=' after attribute name.", start, end, line));
-                }
-            },
-            None => Ok(None),
+        assert_eq!(tags[0].line(), Some(1));
+        assert_eq!(tags[1].line(), Some(3));
+        assert_eq!(tags[1].children()[0].line(), Some(4));
+        assert_eq!(Tag::new(String::from("d")).line(), None);
+    }
+
+    #[test]
+    fn unicode_names_and_values() {
+        let tags = parse("設定 { 名前 \"値\"; }").unwrap();
+
+        assert_eq!(tags[0].name, "設定");
+        assert_eq!(tags[0].children[0].name, "名前");
+        assert_eq!(
+            tags[0].children[0].values,
+            vec![Value::String(String::from("値"))]
+        );
+    }
+
+    fn parse_with(source: &str, options: ParserOptions) -> Result<Vec<Tag>, Error> {
+        Parser::with_options(Scanner::new(source), options).parse()
+    }
+
+    #[test]
+    fn duplicate_attributes() {
+        let source = "a k=1 k=2;";
+        let first = ParserOptions {
+            duplicate_attributes: DuplicateAttributes::KeepFirst,
+            ..ParserOptions::default()
+        };
+        let error = ParserOptions {
+            duplicate_attributes: DuplicateAttributes::Error,
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(
+            parse(source).unwrap()[0].attributes["k"],
+            Value::Integer(2, Radix::Decimal)
+        );
+        assert_eq!(
+            parse_with(source, first).unwrap()[0].attributes["k"],
+            Value::Integer(1, Radix::Decimal)
+        );
+        assert_eq!(
+            parse_with(source, error).unwrap_err(),
+            Error("Duplicate attribute.", 6, 7, 1, ErrorKind::Syntax)
+        );
+    }
+
+    #[test]
+    fn max_depth() {
+        let options = ParserOptions {
+            max_depth: Some(2),
+            ..ParserOptions::default()
+        };
+
+        assert!(parse_with("a { b { c 1; } }", options).is_ok());
+        assert_eq!(
+            parse_with("a { b { c { d 1; } } }", options).unwrap_err(),
+            Error(
+                "Maximum nesting depth exceeded.",
+                10,
+                11,
+                1,
+                ErrorKind::Limit(Limit::Depth)
+            )
+        );
+    }
+
+    #[test]
+    fn default_max_depth() {
+        let source = format!("{}{}", "a {".repeat(257), "}".repeat(257));
+
+        assert_eq!(
+            parse(&source).unwrap_err(),
+            Error(
+                "Maximum nesting depth exceeded.",
+                770,
+                771,
+                1,
+                ErrorKind::Limit(Limit::Depth)
+            )
+        );
+        assert!(parse(&source[3..source.len() - 1]).is_ok());
+    }
+
+    #[test]
+    fn unlimited_depth() {
+        let depth = 200_000;
+        let source = format!("{}{}", "a{".repeat(depth), "}".repeat(depth));
+        let options = ParserOptions {
+            max_depth: None,
+            ..ParserOptions::default()
+        };
+        let mut tags = parse_with(&source, options).unwrap();
+
+        let mut levels = 0;
+        while let Some(mut tag) = tags.pop() {
+            levels += 1;
+            tags.append(&mut tag.children);
         }
+        assert_eq!(levels, depth);
     }
 
-    fn attribute_or_literal(&mut self) -> Result<Option<(Option<String>, Value)>, Error> {
-        let attribute = self.attribute()?;
+    #[test]
+    fn drops_deep_trees() {
+        let mut root = Tag::new(String::from("a"));
 
-        match attribute {
-            Some(attr) => Ok(Some((Some(attr.0), attr.1))),
-            None => {
-                let literal = self.literal()?;
-                match literal {
-                    Some(value) => Ok(Some((None, value))),
-                    None => Ok(None),
-                }
-            }
+        for _ in 0..200_000 {
+            let mut parent = Tag::new(String::from("a"));
+            parent.children.push(root);
+            root = parent;
         }
     }
 
-    fn tag_declaration(&mut self) -> Result<Tag, Error> {
-        let identifier = self.identifier()?;
+    #[test]
+    fn value_order() {
+        let mut values = vec![
+            Value::from("a"),
+            Value::Float(f64::NAN),
+            Value::Float(1.5),
+            Value::Float(0.0),
+            Value::Float(-0.0),
+            Value::Long(3_000_000_000, Radix::Decimal),
+            Value::Integer(-1, Radix::Decimal),
+            Value::Boolean(true),
+            Value::Boolean(false),
+            Value::Null,
+        ];
+        let sorted = values.clone();
+        values.reverse();
+        values.sort();
 
-        match identifier {
-            Some(name) => {
-                let mut tag = Tag::new(name);
+        assert_eq!(values, sorted.into_iter().rev().collect::<Vec<_>>());
+        assert_eq!(Value::Float(f64::NAN), Value::Float(f64::NAN));
+        assert_ne!(Value::Float(0.0), Value::Float(-0.0));
+        assert_eq!(
+            Value::Integer(16, Radix::Hexadecimal),
+            Value::Long(16, Radix::Decimal)
+        );
 
-                loop {
-                    match self.current {
-                        Token::Semicolon(_, _, _) | Token::LeftBrace(_, _, _) => break,
-                        Token::Eof(s, e, l) => {
-                            return Err(Error("Expect literal value or attribute.", s, e, l))
-                        }
-                        _ => {
-                            let attr_or_literal = self.attribute_or_literal()?;
-
-                            match attr_or_literal {
-                                Some((Some(name), value)) => {
-                                    tag.attributes.insert(name, value);
-                                }
-                                Some((None, value)) => {
-                                    tag.values.push(value);
-                                }
-                                None => {
-                                    let (s, e, l) = self.current.position();
-                                    return Err(Error(
-                                        "Expect literal value or attribute.",
-                                        s,
-                                        e,
-                                        l,
-                                    ));
-                                }
-                            }
-                        }
-                    }
-                }
+        let set: std::collections::HashSet<_> = vec![
+            Value::Integer(16, Radix::Hexadecimal),
+            Value::Long(16, Radix::Decimal),
+            Value::Float(f64::NAN),
+            Value::Float(f64::NAN),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(set.len(), 2);
+    }
 
-                match self.current {
-                    Token::Semicolon(s, e, l) => {
-                        if tag.values.len() == 0 && tag.attributes.len() == 0 {
-                            return Err(Error("Expect literal value or attribute.", s, e, l));
-                        }
+    #[test]
+    fn display_tree() {
+        let tags = parse(
+            "scripts \"run\" 2 z=true a-b=1 a=31 {\n  build { prod \"microbundle\"; }\n  test;\n}",
+        )
+        .unwrap();
 
-                        self.advance();
-                        Ok(tag)
-                    }
-                    Token::LeftBrace(..) => {
-                        self.advance();
-                        loop {
-                            match self.current {
-                                Token::RightBrace(..) => {
-                                    self.advance();
-                                    break;
-                                }
-                                Token::Eof(s, e, l) => {
-                                    return Err(Error("Expect '}' after tag body.", s, e, l))
-                                }
-                                _ => {
-                                    let child_tag = self.tag_declaration()?;
-                                    tag.children.push(child_tag);
-                                }
-                            }
-                        }
+        assert_eq!(
+            tags[0].to_string(),
+            "Tag scripts {
+  values: \"run\", 2
+  attributes: a=31, a-b=1, z=true
+  children:
+    Tag build {
+      children:
+        Tag prod {
+          values: \"microbundle\"
+        }
+    }
+    Tag test {}
+}
+"
+        );
+    }
 
-                        Ok(tag)
-                    }
-                    Token::Eof(s, e, l) => Err(Error("Expect ';' or '{'.", s, e, l)),
-                    _ => {
-                        let (s, e, l) = self.current.position();
-                        Err(Error("Expect ';' or '{'.", s, e, l))
-                    }
-                }
-            }
-            None => {
-                let (s, e, l) = self.current.position();
-                Err(Error("Expect identifier.", s, e, l))
+    #[test]
+    fn tag_equality() {
+        let tags = parse("a 1 x=1 y=2 { b; }\na 1 y=2 x=1 { b; }\na 1 x=1 y=2;").unwrap();
+
+        assert_eq!(tags[0], tags[1]);
+        assert_ne!(tags[0], tags[2]);
+        assert!(tags[0].attributes_eq(&tags[2]));
+        assert_eq!(tags[0].clone(), tags[0]);
+        assert_eq!(tags[0].clone().line(), Some(1));
+    }
+
+    #[test]
+    fn edits_tags() {
+        let mut tags = parse("scripts { build 1; lint 2; build 3; }").unwrap();
+        let scripts = &mut tags[0];
+
+        scripts.set_name("tasks");
+        scripts.push_value(Value::Boolean(true));
+        assert_eq!(scripts.set_attribute("parallel", Value::Null), None);
+        assert_eq!(
+            scripts.set_attribute("parallel", Value::Boolean(false)),
+            Some(Value::Null)
+        );
+        *scripts.attribute_mut("parallel").unwrap() = Value::Boolean(true);
+        assert_eq!(scripts.attribute("parallel"), Some(&Value::Boolean(true)));
+
+        scripts
+            .child_mut("lint")
+            .unwrap()
+            .values_mut()
+            .push(Value::Null);
+        assert_eq!(scripts.children_named("build").count(), 2);
+
+        let removed = scripts.remove_children_named("build");
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[1].values(), &[Value::Integer(3, Radix::Decimal)]);
+
+        scripts.add_child(Tag::new(String::from("test")));
+        scripts.retain_children(|child| child.name() != "lint");
+
+        assert_eq!(scripts.name(), "tasks");
+        assert_eq!(scripts.values(), &[Value::Boolean(true)]);
+        assert_eq!(scripts.children().len(), 1);
+        assert!(scripts.child("test").is_some());
+        assert_eq!(
+            scripts.remove_attribute("parallel"),
+            Some(Value::Boolean(true))
+        );
+        assert!(scripts.attributes().is_empty());
+    }
+
+    #[test]
+    fn max_size() {
+        let options = ParserOptions {
+            max_size: Some(9),
+            ..ParserOptions::default()
+        };
+
+        assert!(parse_with("a 1; b 2;", options).is_ok());
+        assert_eq!(
+            parse_with("a 1; b 22;", options).unwrap_err(),
+            Error(
+                "Document exceeds maximum size.",
+                9,
+                9,
+                1,
+                ErrorKind::Limit(Limit::Size)
+            )
+        );
+        assert_eq!(
+            parse_with("a 1; b 22;", options).unwrap_err().kind(),
+            ErrorKind::Limit(Limit::Size)
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let options = ParserOptions {
+            max_tags: Some(3),
+            max_values: Some(2),
+            max_attributes: Some(2),
+            max_string_length: Some(8),
+            ..ParserOptions::default()
+        };
+        let kind = |source: &str| parse_with(source, options).unwrap_err().kind();
+
+        assert!(parse_with(r#"a 1 "12345678" k=1 j=2 { b; c; }"#, options).is_ok());
+        assert_eq!(kind("a; b; c; d;"), ErrorKind::Limit(Limit::Tags));
+        assert_eq!(kind("a 1 2 3;"), ErrorKind::Limit(Limit::Values));
+        assert_eq!(kind("a i=1 j=2 k=3;"), ErrorKind::Limit(Limit::Attributes));
+        assert_eq!(
+            kind(r#"a "123456789";"#),
+            ErrorKind::Limit(Limit::StringLength)
+        );
+        assert_eq!(
+            parse(&"a {".repeat(300)).unwrap_err().kind(),
+            ErrorKind::Limit(Limit::Depth)
+        );
+        assert_eq!(kind("a 1 k=;"), ErrorKind::Syntax);
+    }
+
+    #[test]
+    fn input_errors() {
+        let mut parser = Parser::from_reader(&b"a \xff;"[..]);
+
+        assert_eq!(parser.parse().unwrap_err().kind(), ErrorKind::Input);
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let source = "a 2147483648;";
+        let widen = ParserOptions {
+            integer_overflow: IntegerOverflow::Widen,
+            ..ParserOptions::default()
+        };
+        let saturate = ParserOptions {
+            integer_overflow: IntegerOverflow::Saturate,
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(
+            parse(source).unwrap_err(),
+            Error("Integer literal out of range.", 2, 12, 1, ErrorKind::Syntax)
+        );
+        assert_eq!(
+            parse_with(source, widen).unwrap()[0].values,
+            vec![Value::Long(2147483648, Radix::Decimal)]
+        );
+        assert_eq!(
+            parse_with(source, saturate).unwrap()[0].values,
+            vec![Value::Integer(i32::MAX, Radix::Decimal)]
+        );
+        assert_eq!(
+            parse_with("a -2147483649;", saturate).unwrap()[0].values,
+            vec![Value::Integer(i32::MIN, Radix::Decimal)]
+        );
+        assert_eq!(
+            parse_with("a -2147483649;", widen).unwrap()[0].values,
+            vec![Value::Long(-2147483649, Radix::Decimal)]
+        );
+    }
+
+    #[test]
+    fn negative_numbers() {
+        let options = ParserOptions {
+            extensions: true,
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(
+            parse_with("offset -5 -2147483648 -1.5 -0x1F;", options).unwrap()[0].values,
+            vec![
+                Value::Integer(-5, Radix::Decimal),
+                Value::Integer(i32::MIN, Radix::Decimal),
+                Value::Float(-1.5),
+                Value::Integer(-31, Radix::Hexadecimal),
+            ]
+        );
+    }
+
+    #[test]
+    fn newline_terminators() {
+        let options = ParserOptions {
+            newline_terminators: true,
+            ..ParserOptions::default()
+        };
+        let source = "a 1 // one\n\nb {\n  c 2 \\\n    3; d\n}\ne k=true\n";
+
+        assert_eq!(
+            parse_with(source, options).unwrap(),
+            parse("a 1; b { c 2 3; d; } e k=true;").unwrap()
+        );
+        assert_eq!(
+            parse("a 1\nb 2;").unwrap_err(),
+            Error(
+                "Expect '=' after attribute name.",
+                6,
+                7,
+                2,
+                ErrorKind::Syntax
+            )
+        );
+    }
+
+    #[test]
+    fn empty_tags() {
+        let options = ParserOptions {
+            allow_empty_tags: false,
+            ..ParserOptions::default()
+        };
+        let tags = parse("enabled; section {}").unwrap();
+
+        assert!(tags[0].values.is_empty() && tags[0].attributes.is_empty());
+        assert!(tags[1].values.is_empty() && tags[1].children.is_empty());
+        assert!(tags[0].is_flag() && tags[1].is_flag());
+
+        let tags = parse("build { release; debug false; docs { pdf; } }").unwrap();
+        assert!(tags[0].has_flag("release"));
+        assert!(!tags[0].has_flag("debug"));
+        assert!(!tags[0].has_flag("docs"));
+        assert!(!tags[0].has_flag("missing"));
+        assert_eq!(
+            parse_with("enabled;", options).unwrap_err(),
+            Error(
+                "Expect literal value or attribute.",
+                7,
+                8,
+                1,
+                ErrorKind::Syntax
+            )
+        );
+    }
+
+    #[test]
+    fn malformed_number() {
+        assert_eq!(
+            parse("port 80abc;").unwrap_err(),
+            Error("Invalid numeric literal.", 5, 10, 1, ErrorKind::Syntax)
+        );
+    }
+
+    #[test]
+    fn float_keywords() {
+        let options = ParserOptions {
+            float_keywords: true,
+            ..ParserOptions::default()
+        };
+        let tags = parse_with("threshold nan inf 1e3;", options).unwrap();
+
+        match tags[0].values[..] {
+            [Value::Float(nan), Value::Float(inf), Value::Float(thousand)] => {
+                assert!(nan.is_nan());
+                assert_eq!(inf, f64::INFINITY);
+                assert_eq!(thousand, 1000.0);
             }
+            ref values => panic!("unexpected values {:?}", values),
         }
     }
 
-    fn advance(&mut self) -> Token {
-        let previous = self.current;
-        let span = cmp::min(0, self.scanner.source_length() - 2);
-        let line = self.scanner.curr_line();
-        self.current = self
-            .scanner
-            .next()
-            .unwrap_or(Token::Eof(span, span + 1, line));
-        previous
-    }
-
-    fn print_error(&self, msg: &str, start: usize, end: usize, line: usize) {
-        let mut report = String::new();
-        let source_length = self.scanner.source_length();
-        let lines: Vec<_> = self
-            .scanner
-            .source_slice(end, source_length)
-            .split("\n")
+    #[test]
+    fn radix_integers() {
+        let options = ParserOptions {
+            extensions: true,
+            integer_overflow: IntegerOverflow::Widen,
+            ..ParserOptions::default()
+        };
+        let tags = parse_with("regs 0xFF00 0o755 0b1010 1_000 0xFFFF_FFFF;", options).unwrap();
+
+        assert_eq!(
+            tags[0].values,
+            vec![
+                Value::Integer(0xFF00, Radix::Hexadecimal),
+                Value::Integer(0o755, Radix::Octal),
+                Value::Integer(0b1010, Radix::Binary),
+                Value::Integer(1000, Radix::Decimal),
+                Value::Long(0xFFFF_FFFF, Radix::Hexadecimal),
+            ]
+        );
+        let radixes: Vec<_> = tags[0]
+            .values
+            .iter()
+            .filter_map(|value| match value {
+                Value::Integer(_, radix) | Value::Long(_, radix) => Some(*radix),
+                _ => None,
+            })
             .collect();
-        let rctx = lines.first().unwrap_or(&"");
-
-        report.push_str(format!("Syntax error at line {}: {}\n", line, msg).as_str());
-        report.push_str("   |\n");
-        report.push_str(
-            format!(
-                "{}  | {}{}\n",
-                line,
-                self.scanner.source_slice(start, end),
-                rctx
+        assert_eq!(
+            radixes,
+            vec![
+                Radix::Hexadecimal,
+                Radix::Octal,
+                Radix::Binary,
+                Radix::Decimal,
+                Radix::Hexadecimal,
+            ]
+        );
+        assert!(parse("mask 0xFF00;").is_err());
+    }
+
+    #[test]
+    fn reports_first_error() {
+        assert_eq!(
+            parse("a 1; b x;").unwrap_err(),
+            Error(
+                "Expect '=' after attribute name.",
+                8,
+                9,
+                1,
+                ErrorKind::Syntax
             )
-            .as_str(),
         );
-        print!("{}", report);
-        println!("   |{}\n", format!("{:>w$}", "^", w = 2));
-    }
-
-    pub fn parse(mut self) -> Vec<Tag> {
-        loop {
-            match self.current {
-                Token::Eof(..) => break,
-                _ => match self.tag_declaration() {
-                    Ok(tag) => self.tags.push(tag),
-                    Err(Error(msg, start, end, line)) => {
-                        self.print_error(msg, start, end, line);
-                        break;
-                    }
-                },
+    }
+
+    // Tokens for `name 1;` with fixed text, as an external lexer might produce.
+    struct Fixed(Vec<Token>);
+
+    impl Iterator for Fixed {
+        type Item = Token;
+
+        fn next(&mut self) -> Option<Token> {
+            if self.0.is_empty() {
+                None
+            } else {
+                Some(self.0.remove(0))
             }
         }
-        self.tags
+    }
+
+    impl TokenSource for Fixed {
+        fn source_slice(&self, start: usize, end: usize) -> &str {
+            &"name 1;"[start..end]
+        }
+
+        fn eof(&self) -> Token {
+            Token::Eof(7, 7, 1)
+        }
+    }
+
+    #[test]
+    fn custom_token_source() {
+        let tokens = Fixed(vec![
+            Token::Identifier(0, 4, 1),
+            Token::Integer(5, 6, 1),
+            Token::Semicolon(6, 7, 1),
+        ]);
+        let tags = Parser::new(tokens).parse().unwrap();
+
+        assert_eq!(tags[0].name, "name");
+        assert_eq!(tags[0].values, vec![Value::Integer(1, Radix::Decimal)]);
+    }
+
+    #[test]
+    fn borrowed_scanner() {
+        let mut scanner = Scanner::new("a 1; b 2;");
+        let tags = Parser::new(&mut scanner).parse().unwrap();
+
+        assert_eq!(tags.len(), 2);
+        assert_eq!(scanner.next(), None);
     }
 }
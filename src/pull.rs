@@ -0,0 +1,788 @@
+use crate::options::{DuplicateAttributes, IntegerOverflow, ParserOptions};
+use crate::parser::{Error, ErrorKind, Limit, Radix, Value, Warning};
+use crate::scanner::*;
+use crate::source::{ReadSource, StrSource};
+use std::collections::HashSet;
+use std::io::BufRead;
+
+/// A single step of a document, in source order.
+///
+/// Every tag produces a `StartTag`, followed by its values and attributes.
+/// A tag with a body then produces `StartChildren`, the events of its
+/// children, and finally `EndTag`; a tag ended by `;` produces `EndTag`
+/// right after its values and attributes.
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    StartTag { name: String },
+    Value(Value),
+    Attribute { name: String, value: Value },
+    StartChildren,
+    EndTag,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum State {
+    // Expecting the next tag name, or '}' when inside a body.
+    Tags,
+    // Expecting values and attributes of the current tag.
+    Contents,
+    Done,
+}
+
+/// Pull parser yielding `Event`s without building a tree.
+///
+/// Memory use does not grow with the size of the document: only the
+/// current token, the nesting depth and the attribute names of the current
+/// tag are kept, so it grows with the number of attributes of a tag.
+pub struct PullParser<T> {
+    tokens: T,
+    options: ParserOptions,
+    current: Token,
+    state: State,
+    // Span of the token that began the last event.
+    position: (usize, usize, usize),
+    depth: usize,
+    tags: usize,
+    // Values and attribute names of the current tag.
+    values: usize,
+    attributes: HashSet<String>,
+    warnings: Vec<Warning>,
+}
+
+impl<T: TokenSource> Iterator for PullParser<T> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = match self.state {
+            State::Tags => self.tag(),
+            State::Contents => self.contents(),
+            State::Done => return None,
+        };
+
+        match event {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.state = State::Done;
+                None
+            }
+            Err(error) => {
+                self.state = State::Done;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+impl<'a> PullParser<Scanner<StrSource<'a>>> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(source: &'a str) -> Self {
+        PullParser::new(Scanner::new(source))
+    }
+}
+
+impl<R: BufRead> PullParser<Scanner<ReadSource<R>>> {
+    pub fn from_reader(reader: R) -> Self {
+        PullParser::new(Scanner::from_reader(reader))
+    }
+}
+
+impl<T: TokenSource> PullParser<T> {
+    pub fn new(tokens: T) -> Self {
+        PullParser::with_options(tokens, ParserOptions::default())
+    }
+
+    pub fn with_options(mut tokens: T, options: ParserOptions) -> Self {
+        tokens.configure(&options);
+        let current = next_token(&mut tokens);
+        PullParser {
+            tokens,
+            options,
+            current,
+            state: State::Tags,
+            position: current.position(),
+            depth: 0,
+            tags: 0,
+            values: 0,
+            attributes: HashSet::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Rule violations accepted so far outside of strict mode.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Removes and returns the warnings collected so far.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Span of the token that began the last event: the name of a tag, a
+    /// value, the name of an attribute, or the `{`, `;` or `}` of a body.
+    pub fn position(&self) -> (usize, usize, usize) {
+        self.position
+    }
+
+    /// Nesting depth of the tag whose contents are being read.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn advance(&mut self) -> Token {
+        let previous = self.current;
+        self.current = next_token(&mut self.tokens);
+        previous
+    }
+
+    fn text(&self, start: usize, end: usize) -> String {
+        String::from(self.tokens.source_slice(start, end))
+    }
+
+    fn identifier(&mut self) -> Result<Option<String>, Error> {
+        match self.current {
+            Token::Identifier(s, e, _) => {
+                let name = self.text(s, e);
+                self.advance();
+                Ok(Some(name))
+            }
+            Token::Error(msg, s, e, l, kind) => Err(Error(msg, s, e, l, kind)),
+            _ => Ok(None),
+        }
+    }
+
+    fn integer(&self, s: usize, e: usize, l: usize) -> Result<Value, Error> {
+        let text = self.tokens.source_slice(s, e);
+        let (sign, text) = match text.strip_prefix('-') {
+            Some(text) => ("-", text),
+            None => ("", text),
+        };
+        let (radix, digits) = match text.get(..2) {
+            Some("0x") => (Radix::Hexadecimal, &text[2..]),
+            Some("0o") => (Radix::Octal, &text[2..]),
+            Some("0b") => (Radix::Binary, &text[2..]),
+            _ => (Radix::Decimal, text),
+        };
+        let digits = format!("{}{}", sign, digits.replace('_', ""));
+
+        if let Ok(int) = i32::from_str_radix(&digits, radix.base()) {
+            return Ok(Value::Integer(int, radix));
+        }
+
+        match self.options.integer_overflow {
+            IntegerOverflow::Error => Err(Error(
+                "Integer literal out of range.",
+                s,
+                e,
+                l,
+                ErrorKind::Syntax,
+            )),
+            IntegerOverflow::Widen => match i64::from_str_radix(&digits, radix.base()) {
+                Ok(long) => Ok(Value::Long(long, radix)),
+                Err(_) => Err(Error(
+                    "Integer literal out of range.",
+                    s,
+                    e,
+                    l,
+                    ErrorKind::Syntax,
+                )),
+            },
+            IntegerOverflow::Saturate if sign.is_empty() => Ok(Value::Integer(i32::MAX, radix)),
+            IntegerOverflow::Saturate => Ok(Value::Integer(i32::MIN, radix)),
+        }
+    }
+
+    fn literal(&mut self) -> Result<Option<Value>, Error> {
+        let value = match self.current {
+            Token::Integer(s, e, l) => self.integer(s, e, l)?,
+            Token::String(s, e, _) => Value::String(self.text(s, e)),
+            Token::Float64(s, e, l) => {
+                match self.tokens.source_slice(s, e).replace('_', "").parse() {
+                    Ok(float) => Value::Float(float),
+                    Err(_) => {
+                        return Err(Error("Invalid float literal.", s, e, l, ErrorKind::Syntax))
+                    }
+                }
+            }
+            Token::True(..) => Value::Boolean(true),
+            Token::False(..) => Value::Boolean(false),
+            Token::Null(..) => Value::Null,
+            Token::Error(msg, s, e, l, kind) => return Err(Error(msg, s, e, l, kind)),
+            _ => return Ok(None),
+        };
+
+        self.advance();
+        Ok(Some(value))
+    }
+
+    fn attribute_or_literal(&mut self) -> Result<Event, Error> {
+        match self.identifier()? {
+            Some(name) => match self.current {
+                Token::Equal(s, e, l) => {
+                    self.advance();
+
+                    match self.literal()? {
+                        Some(value) => Ok(Event::Attribute { name, value }),
+                        None => Err(Error(
+                            "Expect literal after '='.",
+                            s,
+                            e,
+                            l,
+                            ErrorKind::Syntax,
+                        )),
+                    }
+                }
+                Token::Eof(s, e, l) => {
+                    Err(Error("Unexpected identifier.", s, e, l, ErrorKind::Syntax))
+                }
+                Token::Error(msg, s, e, l, kind) => Err(Error(msg, s, e, l, kind)),
+                ref t => {
+                    let (s, e, l) = t.position();
+                    Err(Error(
+                        "Expect '=' after attribute name.",
+                        s,
+                        e,
+                        l,
+                        ErrorKind::Syntax,
+                    ))
+                }
+            },
+            None => match self.literal()? {
+                Some(value) => Ok(Event::Value(value)),
+                None => {
+                    let (s, e, l) = self.current.position();
+                    Err(Error(
+                        "Expect literal value or attribute.",
+                        s,
+                        e,
+                        l,
+                        ErrorKind::Syntax,
+                    ))
+                }
+            },
+        }
+    }
+
+    fn tag(&mut self) -> Result<Option<Event>, Error> {
+        // Blank lines, and the newlines after `{` and `}`.
+        if self.options.newline_terminators {
+            while let Token::Semicolon(..) = self.current {
+                self.advance();
+            }
+        }
+
+        self.position = self.current.position();
+
+        match self.current {
+            Token::Eof(s, e, l) if self.depth > 0 => Err(Error(
+                "Expect '}' after tag body.",
+                s,
+                e,
+                l,
+                ErrorKind::Syntax,
+            )),
+            Token::Eof(..) => Ok(None),
+            Token::RightBrace(..) if self.depth > 0 => {
+                self.advance();
+                self.depth -= 1;
+                Ok(Some(Event::EndTag))
+            }
+            Token::Identifier(s, e, l) if at_limit(self.tags, self.options.max_tags) => Err(Error(
+                "Maximum number of tags exceeded.",
+                s,
+                e,
+                l,
+                ErrorKind::Limit(Limit::Tags),
+            )),
+            _ => match self.identifier()? {
+                Some(name) => {
+                    self.tags += 1;
+                    self.state = State::Contents;
+                    self.values = 0;
+                    self.attributes.clear();
+                    Ok(Some(Event::StartTag { name }))
+                }
+                None => {
+                    let (s, e, l) = self.current.position();
+                    Err(Error("Expect identifier.", s, e, l, ErrorKind::Syntax))
+                }
+            },
+        }
+    }
+
+    fn contents(&mut self) -> Result<Option<Event>, Error> {
+        loop {
+            if let Some(event) = self.content()? {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    // Reads the next value, attribute or end of the tag contents. Returns
+    // `None` for attributes dropped as duplicates.
+    fn content(&mut self) -> Result<Option<Event>, Error> {
+        self.position = self.current.position();
+
+        match self.current {
+            Token::Semicolon(s, e, l) => {
+                if !self.options.allow_empty_tags && self.values == 0 && self.attributes.is_empty()
+                {
+                    return Err(Error(
+                        "Expect literal value or attribute.",
+                        s,
+                        e,
+                        l,
+                        ErrorKind::Syntax,
+                    ));
+                }
+
+                self.advance();
+                self.state = State::Tags;
+                Ok(Some(Event::EndTag))
+            }
+            Token::LeftBrace(s, e, l) => {
+                if let Some(max) = self.options.max_depth {
+                    if self.depth >= max {
+                        return Err(Error(
+                            "Maximum nesting depth exceeded.",
+                            s,
+                            e,
+                            l,
+                            ErrorKind::Limit(Limit::Depth),
+                        ));
+                    }
+                }
+
+                self.advance();
+                self.depth += 1;
+                self.state = State::Tags;
+                Ok(Some(Event::StartChildren))
+            }
+            Token::Eof(s, e, l) => Err(Error(
+                "Expect literal value or attribute.",
+                s,
+                e,
+                l,
+                ErrorKind::Syntax,
+            )),
+            _ => {
+                let (s, e, l) = self.current.position();
+                let event = self.attribute_or_literal()?;
+
+                match event {
+                    Event::Value(_) => {
+                        if at_limit(self.values, self.options.max_values) {
+                            return Err(Error(
+                                "Maximum number of values exceeded.",
+                                s,
+                                e,
+                                l,
+                                ErrorKind::Limit(Limit::Values),
+                            ));
+                        }
+                        if !self.attributes.is_empty() {
+                            self.violation("Values must come before attributes.", s, e, l)?;
+                        }
+                        self.values += 1;
+                    }
+                    Event::Attribute { ref name, .. } => {
+                        if self.attributes.contains(name) {
+                            match self.options.duplicate_attributes {
+                                DuplicateAttributes::Replace => (),
+                                DuplicateAttributes::KeepFirst => return Ok(None),
+                                DuplicateAttributes::Error => {
+                                    return Err(Error(
+                                        "Duplicate attribute.",
+                                        s,
+                                        e,
+                                        l,
+                                        ErrorKind::Syntax,
+                                    ))
+                                }
+                            }
+                        } else if at_limit(self.attributes.len(), self.options.max_attributes) {
+                            return Err(Error(
+                                "Maximum number of attributes exceeded.",
+                                s,
+                                e,
+                                l,
+                                ErrorKind::Limit(Limit::Attributes),
+                            ));
+                        } else {
+                            self.attributes.insert(name.clone());
+                        }
+                    }
+                    _ => (),
+                }
+
+                Ok(Some(event))
+            }
+        }
+    }
+
+    // Fails in strict mode, otherwise records a warning.
+    fn violation(&mut self, msg: &'static str, s: usize, e: usize, l: usize) -> Result<(), Error> {
+        if self.options.strict {
+            return Err(Error(msg, s, e, l, ErrorKind::Syntax));
+        }
+
+        match self.options.max_warnings {
+            Some(max) if self.warnings.len() > max => (),
+            Some(max) if self.warnings.len() == max => {
+                self.warnings
+                    .push(Warning("Further warnings suppressed.", s, e, l));
+            }
+            _ => self.warnings.push(Warning(msg, s, e, l)),
+        }
+
+        Ok(())
+    }
+}
+
+fn next_token<T: TokenSource>(tokens: &mut T) -> Token {
+    loop {
+        match tokens.next() {
+            Some(Token::Comment(..)) => (),
+            Some(token) => return token,
+            None => return tokens.eof(),
+        }
+    }
+}
+
+fn at_limit(count: usize, max: Option<usize>) -> bool {
+    match max {
+        Some(max) => count >= max,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(source: &str) -> Vec<Result<Event, Error>> {
+        PullParser::from_str(source).collect()
+    }
+
+    #[test]
+    fn tag_with_values_and_attributes() {
+        assert_eq!(
+            events(r#"format prettier=true "prettier";"#),
+            vec![
+                Ok(Event::StartTag {
+                    name: String::from("format")
+                }),
+                Ok(Event::Attribute {
+                    name: String::from("prettier"),
+                    value: Value::Boolean(true)
+                }),
+                Ok(Event::Value(Value::String(String::from("prettier")))),
+                Ok(Event::EndTag),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_tags() {
+        assert_eq!(
+            events("a { b 1; }"),
+            vec![
+                Ok(Event::StartTag {
+                    name: String::from("a")
+                }),
+                Ok(Event::StartChildren),
+                Ok(Event::StartTag {
+                    name: String::from("b")
+                }),
+                Ok(Event::Value(Value::Integer(1, Radix::Decimal))),
+                Ok(Event::EndTag),
+                Ok(Event::EndTag),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_tags() {
+        assert_eq!(
+            events("enabled; section {}"),
+            vec![
+                Ok(Event::StartTag {
+                    name: String::from("enabled")
+                }),
+                Ok(Event::EndTag),
+                Ok(Event::StartTag {
+                    name: String::from("section")
+                }),
+                Ok(Event::StartChildren),
+                Ok(Event::EndTag),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_body() {
+        assert_eq!(
+            events("a {"),
+            vec![
+                Ok(Event::StartTag {
+                    name: String::from("a")
+                }),
+                Ok(Event::StartChildren),
+                Err(Error(
+                    "Expect '}' after tag body.",
+                    3,
+                    3,
+                    1,
+                    ErrorKind::Syntax
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_after_error() {
+        let mut parser = PullParser::from_str("a =; b 1;");
+
+        assert_eq!(
+            parser.next(),
+            Some(Ok(Event::StartTag {
+                name: String::from("a")
+            }))
+        );
+        assert_eq!(
+            parser.next(),
+            Some(Err(Error(
+                "Expect literal value or attribute.",
+                2,
+                3,
+                1,
+                ErrorKind::Syntax
+            )))
+        );
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn events_from_reader() {
+        let source = "scripts {\n  build { prod \"microbundle\"; }\n  lint:fix \"eslint\";\n}\n";
+        let reader = std::io::BufReader::with_capacity(3, source.as_bytes());
+        let streamed: Vec<_> = PullParser::from_reader(reader).collect();
+
+        assert_eq!(streamed, events(source));
+    }
+
+    fn last_error(source: &str, options: ParserOptions) -> Option<Result<Event, Error>> {
+        PullParser::with_options(Scanner::new(source), options).last()
+    }
+
+    #[test]
+    fn errors_after_attribute_name() {
+        let options = ParserOptions {
+            max_size: Some(4),
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            last_error("a b =1;", options),
+            Some(Err(Error(
+                "Document exceeds maximum size.",
+                4,
+                4,
+                1,
+                ErrorKind::Limit(Limit::Size)
+            )))
+        );
+
+        let reader = std::io::BufReader::new(&b"a b\xff=1;"[..]);
+        assert_eq!(
+            PullParser::from_reader(reader).last(),
+            Some(Err(Error("Invalid UTF-8.", 3, 3, 1, ErrorKind::Input)))
+        );
+
+        assert_eq!(
+            last_error("a b \"unterminated", ParserOptions::default()),
+            Some(Err(Error(
+                "Unterminated string.",
+                4,
+                17,
+                1,
+                ErrorKind::Syntax
+            )))
+        );
+    }
+
+    #[test]
+    fn max_tags() {
+        let options = ParserOptions {
+            max_tags: Some(2),
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(last_error("a { b; }", options), Some(Ok(Event::EndTag)));
+        assert_eq!(
+            last_error("a { b; } c;", options),
+            Some(Err(Error(
+                "Maximum number of tags exceeded.",
+                9,
+                10,
+                1,
+                ErrorKind::Limit(Limit::Tags)
+            )))
+        );
+    }
+
+    #[test]
+    fn max_values_and_attributes() {
+        let options = ParserOptions {
+            max_values: Some(2),
+            max_attributes: Some(1),
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(
+            last_error("a 1 2 k=1 k=2;", options),
+            Some(Ok(Event::EndTag))
+        );
+        assert_eq!(
+            last_error("a 1 2 3;", options),
+            Some(Err(Error(
+                "Maximum number of values exceeded.",
+                6,
+                7,
+                1,
+                ErrorKind::Limit(Limit::Values)
+            )))
+        );
+        assert_eq!(
+            last_error("a k=1 j=2;", options),
+            Some(Err(Error(
+                "Maximum number of attributes exceeded.",
+                6,
+                7,
+                1,
+                ErrorKind::Limit(Limit::Attributes)
+            )))
+        );
+    }
+
+    #[test]
+    fn event_positions() {
+        let mut parser = PullParser::from_str("a 1 k=2 {\n  b;\n}");
+        let mut positions = Vec::new();
+
+        while let Some(Ok(_)) = parser.next() {
+            positions.push(parser.position());
+        }
+
+        assert_eq!(
+            positions,
+            vec![
+                (0, 1, 1),
+                (2, 3, 1),
+                (4, 5, 1),
+                (8, 9, 1),
+                (12, 13, 2),
+                (13, 14, 2),
+                (15, 16, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn newline_terminators() {
+        let options = ParserOptions {
+            newline_terminators: true,
+            ..ParserOptions::default()
+        };
+        let source = "a 1\n\nb {\n  c 2 \\\n    3; d\n}\n";
+        let events: Vec<_> = PullParser::with_options(Scanner::new(source), options).collect();
+
+        assert_eq!(
+            events,
+            vec![
+                Ok(Event::StartTag {
+                    name: String::from("a")
+                }),
+                Ok(Event::Value(Value::Integer(1, Radix::Decimal))),
+                Ok(Event::EndTag),
+                Ok(Event::StartTag {
+                    name: String::from("b")
+                }),
+                Ok(Event::StartChildren),
+                Ok(Event::StartTag {
+                    name: String::from("c")
+                }),
+                Ok(Event::Value(Value::Integer(2, Radix::Decimal))),
+                Ok(Event::Value(Value::Integer(3, Radix::Decimal))),
+                Ok(Event::EndTag),
+                Ok(Event::StartTag {
+                    name: String::from("d")
+                }),
+                Ok(Event::EndTag),
+                Ok(Event::EndTag),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_comment_tokens() {
+        let source = "a 1; // one\nb # two\n 2;";
+        let kept: Vec<_> = PullParser::new(Scanner::new(source).keep_comments()).collect();
+
+        assert_eq!(kept, events(source));
+    }
+
+    #[test]
+    fn value_after_attribute_strict() {
+        let options = ParserOptions {
+            strict: true,
+            ..ParserOptions::default()
+        };
+        let events: Vec<_> = PullParser::with_options(Scanner::new("a k=1 2;"), options).collect();
+
+        assert_eq!(
+            events.last(),
+            Some(&Err(Error(
+                "Values must come before attributes.",
+                6,
+                7,
+                1,
+                ErrorKind::Syntax
+            )))
+        );
+    }
+
+    #[test]
+    fn value_after_attribute_lenient() {
+        let mut parser = PullParser::from_str("a 1 k=1 2;");
+
+        assert_eq!(parser.by_ref().filter(Result::is_err).count(), 0);
+        assert_eq!(
+            parser.warnings(),
+            &[Warning("Values must come before attributes.", 8, 9, 1)]
+        );
+    }
+
+    #[test]
+    fn max_warnings() {
+        let mut parser = PullParser::with_options(
+            Scanner::new("a k=1 1; b k=1 2; c k=1 3; d k=1 4;"),
+            ParserOptions {
+                max_warnings: Some(2),
+                ..ParserOptions::default()
+            },
+        );
+
+        assert_eq!(parser.by_ref().filter(Result::is_err).count(), 0);
+        assert_eq!(
+            parser.warnings(),
+            &[
+                Warning("Values must come before attributes.", 6, 7, 1),
+                Warning("Values must come before attributes.", 15, 16, 1),
+                Warning("Further warnings suppressed.", 24, 25, 1),
+            ]
+        );
+
+        parser.take_warnings();
+        assert_eq!(parser.warnings(), &[]);
+    }
+}
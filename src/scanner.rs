@@ -1,9 +1,10 @@
-use std::iter::Peekable;
-use std::str::CharIndices;
+use crate::options::ParserOptions;
+use crate::parser::{ErrorKind, Limit};
+use crate::source::*;
+use std::io::{self, BufRead};
+use unicode_xid::UnicodeXID;
 
-type Index = usize;
 type Line = usize;
-type Char = (Index, char);
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Token {
@@ -14,11 +15,14 @@ pub enum Token {
     Semicolon(usize, usize, Line),
     LeftBrace(usize, usize, Line),
     RightBrace(usize, usize, Line),
-    Error(&'static str, usize, usize, Line),
+    Error(&'static str, usize, usize, Line, ErrorKind),
     String(usize, usize, Line),
     Identifier(usize, usize, Line),
     Float64(usize, usize, Line),
     Integer(usize, usize, Line),
+    /// Only produced by scanners made with `keep_comments`. Includes the
+    /// comment marker.
+    Comment(usize, usize, Line),
     Eof(usize, usize, Line),
 }
 
@@ -36,21 +40,28 @@ impl Token {
             | Token::Identifier(s, e, l)
             | Token::Float64(s, e, l)
             | Token::Integer(s, e, l)
+            | Token::Comment(s, e, l)
             | Token::Eof(s, e, l)
-            | Token::Error(_, s, e, l) => (*s, *e, *l),
+            | Token::Error(_, s, e, l, _) => (*s, *e, *l),
         }
     }
 }
 
-pub struct Scanner<'a> {
-    source: &'a str,
+pub struct Scanner<S> {
+    source: S,
+    options: ParserOptions,
     line: usize,
     start: Option<Char>,
     current: Option<Char>,
-    scanner: Peekable<CharIndices<'a>>,
+    // Set once input beyond `max_size` was cut off.
+    oversized: bool,
+    oversize_reported: bool,
+    keep_comments: bool,
+    // A comment read while skipping a line continuation.
+    pending_comment: Option<Token>,
 }
 
-impl<'a> Iterator for Scanner<'a> {
+impl<S: Source> Iterator for Scanner<S> {
     type Item = Token;
 
     fn next(&mut self) -> Option<Token> {
@@ -58,129 +69,291 @@ impl<'a> Iterator for Scanner<'a> {
     }
 }
 
-impl<'a> Scanner<'a> {
+/// A stream of tokens along with their text, as consumed by the parsers.
+///
+/// `Scanner` is the usual implementation; other implementations can feed
+/// the parsers tokens produced some other way.
+pub trait TokenSource: Iterator<Item = Token> {
+    /// Text between two byte offsets of the token last returned by `next`.
+    fn source_slice(&self, start: usize, end: usize) -> &str;
+
+    /// The token reported once `next` returns `None`.
+    fn eof(&self) -> Token;
+
+    /// Applies the options relevant to scanning. Called by the parsers
+    /// before the first token is read.
+    fn configure(&mut self, _options: &ParserOptions) {}
+}
+
+impl<S: Source> TokenSource for Scanner<S> {
+    fn source_slice(&self, start: usize, end: usize) -> &str {
+        self.source.slice(start, end)
+    }
+
+    fn eof(&self) -> Token {
+        let end = self.source.position();
+        Token::Eof(end, end, self.line)
+    }
+
+    fn configure(&mut self, options: &ParserOptions) {
+        self.options = *options;
+
+        if let Some((index, _)) = self.current {
+            if self.exceeds_size(index) {
+                self.current = None;
+                self.oversized = true;
+            }
+        }
+    }
+}
+
+impl<T: TokenSource + ?Sized> TokenSource for &mut T {
+    fn source_slice(&self, start: usize, end: usize) -> &str {
+        (**self).source_slice(start, end)
+    }
+
+    fn eof(&self) -> Token {
+        (**self).eof()
+    }
+
+    fn configure(&mut self, options: &ParserOptions) {
+        (**self).configure(options)
+    }
+}
+
+impl<'a> Scanner<StrSource<'a>> {
     pub fn new(source: &'a str) -> Self {
-        let mut scanner = source.char_indices().peekable();
-        let first_char = scanner.next();
+        Scanner::with_source(StrSource::new(source))
+    }
+}
+
+impl<R: BufRead> Scanner<ReadSource<R>> {
+    /// Scans incrementally from `reader`. Token positions are the same as
+    /// for the whole input passed to `Scanner::new`.
+    pub fn from_reader(reader: R) -> Self {
+        Scanner::with_source(ReadSource::new(reader))
+    }
+
+    /// The error that ended the input early, if any.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.source.io_error()
+    }
+}
+
+impl<S: Source> Scanner<S> {
+    pub fn with_source(mut source: S) -> Self {
+        let first_char = source.next_char();
         Scanner {
             source,
+            options: ParserOptions::default(),
             start: first_char,
             current: first_char,
             line: 1,
-            scanner,
+            oversized: false,
+            oversize_reported: false,
+            keep_comments: false,
+            pending_comment: None,
         }
     }
 
+    /// Returns comments as `Token::Comment` instead of skipping them. The
+    /// parsers ignore comment tokens.
+    pub fn keep_comments(mut self) -> Self {
+        self.keep_comments = true;
+        self
+    }
+
     pub fn curr_line(&self) -> usize {
         self.line
     }
 
+    /// Length of the input read so far; the whole input once scanning ends.
     pub fn source_length(&self) -> usize {
-        self.source.len()
+        self.source.position()
     }
 
     fn advance(&mut self) -> Option<Char> {
         let current = self.current;
-        self.current = self.scanner.next();
+        self.current = if self.oversized {
+            None
+        } else {
+            self.source.next_char()
+        };
+
+        if let Some((index, _)) = self.current {
+            if self.exceeds_size(index) {
+                self.current = None;
+                self.oversized = true;
+            }
+        }
+
         current
     }
 
+    fn exceeds_size(&self, index: usize) -> bool {
+        match self.options.max_size {
+            Some(max) => index >= max,
+            None => false,
+        }
+    }
+
+    // Consumes a character that is not part of any token.
+    fn skip(&mut self) {
+        self.advance();
+        if let Some((index, _)) = self.current {
+            self.source.discard(index);
+        }
+    }
+
     fn peek(&self) -> Option<char> {
         self.current.map(|c| c.1)
     }
 
     fn peek_next(&mut self) -> Option<char> {
-        self.scanner.peek().map(|c| c.1)
+        self.source.peek_char().map(|c| c.1)
     }
 
-    fn skip_whitespace(&mut self) {
+    // Skips whitespace and comments. Returns an error token for a malformed
+    // line continuation, and the tokens for kept comments and newlines that
+    // end tags.
+    fn skip_whitespace(&mut self) -> Option<Token> {
         while let Some(ch) = self.peek() {
             match ch {
                 ' ' | '\t' | '\r' => {
-                    self.advance();
+                    self.skip();
                 }
                 '\n' => {
+                    let line = self.line;
+                    self.start = self.current;
                     self.line += 1;
-                    self.advance();
+                    self.skip();
+
+                    if self.options.newline_terminators {
+                        let (start, end, _) = self.range();
+                        return Some(Token::Semicolon(start, end, line));
+                    }
                 }
-                '/' => match self.peek_next() {
-                    Some(ch) => {
-                        if ch == '/' {
-                            self.advance();
-                            loop {
-                                match self.peek() {
-                                    Some(ch) if ch != ';' && ch != '\n' => {
-                                        self.advance();
-                                    }
-                                    _ => break,
-                                }
-                            }
-                        } else {
-                            break;
-                        }
+                '/' | '#' | '-' if self.keep_comments && self.at_comment() => {
+                    return Some(self.comment());
+                }
+                '/' | '#' | '-' => {
+                    if !self.skip_comment() {
+                        break;
                     }
-                    _ => break,
-                },
-                '#' => loop {
-                    match self.peek() {
-                        Some(ch) if ch != ';' && ch != '\n' => {
-                            self.advance();
-                        }
-                        _ => break,
+                }
+                '\\' => {
+                    if let Some(error) = self.line_continuation() {
+                        return Some(error);
                     }
-                },
-                '-' => match self.peek_next() {
-                    Some(ch) => {
-                        if ch == '-' {
-                            loop {
-                                match self.peek() {
-                                    Some(ch) if ch != ';' && ch != '\n' => {
-                                        self.advance();
-                                    }
-                                    _ => break,
-                                }
-                            }
-                        }
+                    if let Some(comment) = self.pending_comment.take() {
+                        return Some(comment);
                     }
-                    None => break,
-                },
+                }
                 _ => break,
             }
         }
+
+        None
+    }
+
+    fn at_comment(&mut self) -> bool {
+        let comments = self.options.comments;
+        match self.peek() {
+            Some('#') => comments.hash,
+            Some('/') => comments.double_slash && self.peek_next() == Some('/'),
+            Some('-') => comments.double_dash && self.peek_next() == Some('-'),
+            _ => false,
+        }
+    }
+
+    fn comment(&mut self) -> Token {
+        self.start = self.current;
+
+        while let Some(ch) = self.peek() {
+            if ch == ';' || ch == '\n' {
+                break;
+            }
+            self.advance();
+        }
+
+        let (start, end, line) = self.range();
+        Token::Comment(start, end, line)
+    }
+
+    // Skips a comment running to the end of the line, if one starts here.
+    fn skip_comment(&mut self) -> bool {
+        let starts = self.at_comment();
+
+        if starts {
+            while let Some(ch) = self.peek() {
+                if ch == ';' || ch == '\n' {
+                    break;
+                }
+                self.skip();
+            }
+        }
+
+        starts
+    }
+
+    // Skips a '\\' and the newline ending its line, so that the next line
+    // continues the current one. Only whitespace and a comment may come
+    // between the two.
+    fn line_continuation(&mut self) -> Option<Token> {
+        self.start = self.current;
+        self.skip();
+
+        while let Some(' ' | '\t' | '\r') = self.peek() {
+            self.skip();
+        }
+
+        if self.keep_comments && self.at_comment() {
+            self.pending_comment = Some(self.comment());
+        } else {
+            self.skip_comment();
+        }
+
+        match self.peek() {
+            Some('\n') => {
+                self.line += 1;
+                self.skip();
+                None
+            }
+            None => None,
+            Some(_) => Some(self.make_error("Expect newline after '\\'.")),
+        }
     }
 
     fn range(&self) -> (usize, usize, usize) {
         let (start, _) = self.start.unwrap();
         let end = match self.current {
             Some((index, _)) => index,
-            None => self.source.len(),
+            None => self.source.position(),
         };
 
         (start, end, self.line)
     }
 
+    fn is_identifier_start(&self, ch: char) -> bool {
+        ch.is_xid_start() || ch == '_'
+    }
+
     fn is_valid_char(&self, chr: Option<char>) -> bool {
         if let Some(ch) = chr {
-            return ch.is_ascii_alphabetic()
-                || ch.is_digit(10)
-                || ch == '_'
-                || ch == ':'
-                || ch == '$'
-                || ch == '-';
+            return ch.is_xid_continue() || ch == ':' || ch == '$' || ch == '-' || ch == '.';
         }
         false
     }
 
     fn is_digit(&self, chr: Option<char>) -> bool {
         if let Some(ch) = chr {
-            return ch.is_digit(10);
+            return ch.is_ascii_digit();
         }
         false
     }
 
     fn matches_source(&self, start: usize, end: usize, len: usize, rest: &str) -> bool {
-        let source_rest = &self.source[start..start + len];
-        end - start == len && source_rest == rest
+        end - start == len && self.source.slice(start, end) == rest
     }
 
     fn try_keyword(&self) -> Token {
@@ -191,6 +364,12 @@ impl<'a> Scanner<'a> {
             't' if self.matches_source(start + 1, end, 3, "rue") => Token::True(start, end, line),
             'f' if self.matches_source(start + 1, end, 4, "alse") => Token::False(start, end, line),
             'n' if self.matches_source(start + 1, end, 3, "ull") => Token::Null(start, end, line),
+            'n' if self.options.float_keywords && self.matches_source(start + 1, end, 2, "an") => {
+                Token::Float64(start, end, line)
+            }
+            'i' if self.options.float_keywords && self.matches_source(start + 1, end, 2, "nf") => {
+                Token::Float64(start, end, line)
+            }
             _ => Token::Identifier(start, end, line),
         }
     }
@@ -203,61 +382,159 @@ impl<'a> Scanner<'a> {
         self.try_keyword()
     }
 
+    // Checked before each character of a string, so that an oversized
+    // literal is never held in memory.
+    fn string_too_long(&self) -> bool {
+        match (self.options.max_string_length, self.start, self.current) {
+            (Some(max), Some((start, _)), Some((index, _))) => index - (start + 1) >= max,
+            _ => false,
+        }
+    }
+
     fn make_error(&mut self, msg: &'static str) -> Token {
+        self.make_error_of(ErrorKind::Syntax, msg)
+    }
+
+    fn make_error_of(&mut self, kind: ErrorKind, msg: &'static str) -> Token {
         let (start, end, line) = self.range();
-        Token::Error(msg, start, end, line)
+        Token::Error(msg, start, end, line, kind)
     }
 
-    fn float(&mut self) -> Token {
+    fn digits(&mut self) {
+        while self.is_digit(self.peek()) || self.at_separator() {
+            self.advance();
+        }
+    }
+
+    fn at_separator(&self) -> bool {
+        self.options.extensions && self.peek() == Some('_')
+    }
+
+    // Scans the digits following a `0x`, `0o` or `0b` prefix.
+    fn radix_integer(&mut self, radix: u32) -> Token {
         self.advance();
 
-        match self.peek() {
-            Some(ch) if !ch.is_digit(10) => self.make_error("'.' must be followed by digit."),
-            Some(_) => {
-                while self.is_digit(self.peek()) {
-                    self.advance();
-                }
+        if !matches!(self.peek(), Some(ch) if ch.is_digit(radix)) {
+            return self.make_error("Expect digits after radix prefix.");
+        }
 
-                if let Some(ch) = self.peek() {
-                    if ch == 'e' || ch == 'E' {
-                        self.advance();
-
-                        if let Some(ch) = self.peek() {
-                            if ch == '+' || ch == '-' {
-                                self.advance();
-
-                                while self.is_digit(self.peek()) {
-                                    self.advance();
-                                }
-                            }
-
-                            while self.is_digit(self.peek()) {
-                                self.advance();
-                            }
-                        } else {
-                            return self.make_error("Illegal float.");
-                        }
-                    }
-                }
+        while matches!(self.peek(), Some(ch) if ch.is_digit(radix)) || self.at_separator() {
+            self.advance();
+        }
 
-                let (start, end, line) = self.range();
+        let (start, end, line) = self.range();
+        Token::Integer(start, end, line)
+    }
 
-                return Token::Float64(start, end, line);
-            }
-            _ => self.make_error("'.' must be followed by digit."),
+    // Scans the optional exponent of a float. Returns false if the exponent
+    // has no digits.
+    fn exponent(&mut self) -> bool {
+        self.advance();
+
+        if let Some('+' | '-') = self.peek() {
+            self.advance();
+        }
+
+        if !self.is_digit(self.peek()) {
+            return false;
         }
+
+        self.digits();
+        true
     }
 
+    // number   := '-'? (digits fraction? exponent? | fraction exponent?)
+    // fraction := '.' digits
+    // exponent := ('e' | 'E') ('+' | '-')? digits
+    //
+    // With extensions, digits may contain '_' and integers may be written
+    // as '0x', '0o' or '0b' followed by digits of that radix.
+    //
+    // The first digit, '.' or '-' has already been consumed.
     fn number(&mut self) -> Token {
-        while self.is_digit(self.peek()) {
+        let token = self.numeric_literal();
+
+        if self.at_delimiter() {
+            token
+        } else {
+            self.invalid_number()
+        }
+    }
+
+    // Whether a '-' just consumed starts a negative number rather than a
+    // comment or a stray character.
+    fn at_negative_number(&mut self) -> bool {
+        match self.peek() {
+            Some(ch) if ch.is_ascii_digit() => true,
+            Some('.') => {
+                let next = self.peek_next();
+                self.is_digit(next)
+            }
+            _ => false,
+        }
+    }
+
+    fn numeric_literal(&mut self) -> Token {
+        let (_, mut first) = self.start.unwrap();
+
+        if first == '-' {
+            first = self.advance().map_or(first, |(_, ch)| ch);
+        }
+        let mut float = first == '.';
+
+        if first == '0' && self.options.extensions {
+            match self.peek() {
+                Some('x') => return self.radix_integer(16),
+                Some('o') => return self.radix_integer(8),
+                Some('b') => return self.radix_integer(2),
+                _ => (),
+            }
+        }
+
+        self.digits();
+
+        if !float && self.peek() == Some('.') {
             self.advance();
+
+            if !self.is_digit(self.peek()) {
+                return self.make_error("'.' must be followed by digit.");
+            }
+
+            self.digits();
+            float = true;
+        }
+
+        if let Some('e' | 'E') = self.peek() {
+            if !self.exponent() {
+                return self.make_error("Exponent must have digits.");
+            }
+            float = true;
         }
 
         let (start, end, line) = self.range();
 
+        if float {
+            Token::Float64(start, end, line)
+        } else {
+            Token::Integer(start, end, line)
+        }
+    }
+
+    // Consumes the rest of a malformed number so that it is reported as a
+    // single error.
+    fn invalid_number(&mut self) -> Token {
+        while !self.at_delimiter() {
+            self.advance();
+        }
+
+        self.make_error("Invalid numeric literal.")
+    }
+
+    // Whether the current character can end a number.
+    fn at_delimiter(&mut self) -> bool {
         match self.peek() {
-            Some('.') => self.float(),
-            _ => Token::Integer(start, end, line),
+            None | Some(' ' | '\t' | '\r' | '\n' | ';' | '{' | '}' | '\\') => true,
+            _ => self.at_comment(),
         }
     }
 
@@ -265,6 +542,9 @@ impl<'a> Scanner<'a> {
         loop {
             match self.peek() {
                 Some(ch) if ch != '"' => {
+                    if self.string_too_long() {
+                        return self.skip_string();
+                    }
                     self.advance();
                 }
                 _ => break,
@@ -272,9 +552,8 @@ impl<'a> Scanner<'a> {
         }
 
         // Consume '"'
-        match self.advance() {
-            None => return self.make_error("Unterminated string."),
-            _ => (),
+        if self.advance().is_none() {
+            return self.make_error("Unterminated string.");
         }
 
         let (start, end, line) = self.range();
@@ -282,22 +561,68 @@ impl<'a> Scanner<'a> {
         Token::String(start + 1, end - 1, line)
     }
 
+    // Consumes the rest of an oversized string literal without keeping its
+    // text, so that scanning resumes after the closing quote.
+    fn skip_string(&mut self) -> Token {
+        while let Some(ch) = self.peek() {
+            self.skip();
+            if ch == '"' {
+                break;
+            }
+        }
+
+        self.make_error_of(
+            ErrorKind::Limit(Limit::StringLength),
+            "String exceeds maximum length.",
+        )
+    }
+
+    /// Text between two byte offsets. Scanners over a reader only keep the
+    /// text of the most recently scanned token.
     pub fn source_slice(&self, start: usize, end: usize) -> &str {
-        &self.source[start..end]
+        self.source.slice(start, end)
     }
 
     pub fn scan_token(&mut self) -> Option<Token> {
-        self.skip_whitespace();
+        let token = self.token();
+
+        if self.oversized && !self.oversize_reported {
+            self.oversize_reported = true;
+            let max = self.options.max_size.unwrap_or(0);
+            return Some(Token::Error(
+                "Document exceeds maximum size.",
+                max,
+                max,
+                self.line,
+                ErrorKind::Limit(Limit::Size),
+            ));
+        }
+
+        token
+    }
+
+    fn token(&mut self) -> Option<Token> {
+        if let Some(error) = self.skip_whitespace() {
+            return Some(error);
+        }
 
         self.start = self.current;
 
+        if let Some((index, _)) = self.start {
+            self.source.discard(index);
+        }
+
         match self.advance() {
             Some((_, ch)) => {
-                if ch.is_ascii_alphabetic() || ch == '_' {
+                if self.is_identifier_start(ch) {
                     return Some(self.identifier());
                 }
 
-                if ch.is_digit(10) {
+                if ch.is_ascii_digit() || (ch == '.' && self.is_digit(self.peek())) {
+                    return Some(self.number());
+                }
+
+                if ch == '-' && self.at_negative_number() {
                     return Some(self.number());
                 }
 
@@ -312,7 +637,11 @@ impl<'a> Scanner<'a> {
                     _ => Some(self.make_error("Unexpected character.")),
                 }
             }
-            None => None,
+            None => {
+                let end = self.source.position();
+                let msg = self.source.take_error()?;
+                Some(Token::Error(msg, end, end, self.line, ErrorKind::Input))
+            }
         }
     }
 }
@@ -320,6 +649,7 @@ impl<'a> Scanner<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::options::CommentStyles;
 
     macro_rules! test {
         ($source:expr, $exp:expr) => {
@@ -361,7 +691,13 @@ mod tests {
     fn scan_64_float_error() {
         test!(
             "1.",
-            vec![Token::Error("'.' must be followed by digit.", 0, 2, 1)]
+            vec![Token::Error(
+                "'.' must be followed by digit.",
+                0,
+                2,
+                1,
+                ErrorKind::Syntax
+            )]
         );
     }
 
@@ -369,9 +705,150 @@ mod tests {
     fn scan_64_float_error_2() {
         test!(
             "5.a",
+            vec![Token::Error(
+                "Invalid numeric literal.",
+                0,
+                3,
+                1,
+                ErrorKind::Syntax
+            )]
+        );
+    }
+
+    #[test]
+    fn scan_number_boundaries() {
+        test!(
+            "1;2{3}4 5//c\n6#c",
             vec![
-                Token::Error("'.' must be followed by digit.", 0, 2, 1),
-                Token::Identifier(2, 3, 1),
+                Token::Integer(0, 1, 1),
+                Token::Semicolon(1, 2, 1),
+                Token::Integer(2, 3, 1),
+                Token::LeftBrace(3, 4, 1),
+                Token::Integer(4, 5, 1),
+                Token::RightBrace(5, 6, 1),
+                Token::Integer(6, 7, 1),
+                Token::Integer(8, 9, 1),
+                Token::Integer(13, 14, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_invalid_numbers() {
+        test!(
+            "5abc 1.2.3 7=x 8;",
+            vec![
+                Token::Error("Invalid numeric literal.", 0, 4, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 5, 10, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 11, 14, 1, ErrorKind::Syntax),
+                Token::Integer(15, 16, 1),
+                Token::Semicolon(16, 17, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_float_forms() {
+        test!(
+            "1e5 2E-3 .5 .25e+2 0.5 1.0E10",
+            vec![
+                Token::Float64(0, 3, 1),
+                Token::Float64(4, 8, 1),
+                Token::Float64(9, 11, 1),
+                Token::Float64(12, 18, 1),
+                Token::Float64(19, 22, 1),
+                Token::Float64(23, 29, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_exponent_without_digits() {
+        test!(
+            "1.0e 2e+ 3E;",
+            vec![
+                Token::Error("Exponent must have digits.", 0, 4, 1, ErrorKind::Syntax),
+                Token::Error("Exponent must have digits.", 5, 8, 1, ErrorKind::Syntax),
+                Token::Error("Exponent must have digits.", 9, 11, 1, ErrorKind::Syntax),
+                Token::Semicolon(11, 12, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_malformed_floats() {
+        test!(
+            "1.e5 1e5.0 .5. 1e2e3",
+            vec![
+                Token::Error("Invalid numeric literal.", 0, 4, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 5, 10, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 11, 14, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 15, 20, 1, ErrorKind::Syntax),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_float_keywords() {
+        test!(
+            "nan inf",
+            vec![Token::Identifier(0, 3, 1), Token::Identifier(4, 7, 1)]
+        );
+
+        let mut scanner = Scanner::new("nan inf nano");
+        scanner.configure(&ParserOptions {
+            float_keywords: true,
+            ..ParserOptions::default()
+        });
+
+        assert_eq!(
+            scanner.collect::<Vec<_>>(),
+            vec![
+                Token::Float64(0, 3, 1),
+                Token::Float64(4, 7, 1),
+                Token::Identifier(8, 12, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_radix_integers() {
+        let source = "0xFF00 0o755 0b1010 1_000_000 1_0.5_0 0x 0b102";
+        test!(
+            source,
+            vec![
+                Token::Error("Invalid numeric literal.", 0, 6, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 7, 12, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 13, 19, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 20, 29, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 30, 37, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 38, 40, 1, ErrorKind::Syntax),
+                Token::Error("Invalid numeric literal.", 41, 46, 1, ErrorKind::Syntax),
+            ]
+        );
+
+        let mut scanner = Scanner::new(source);
+        scanner.configure(&ParserOptions {
+            extensions: true,
+            ..ParserOptions::default()
+        });
+
+        assert_eq!(
+            scanner.collect::<Vec<_>>(),
+            vec![
+                Token::Integer(0, 6, 1),
+                Token::Integer(7, 12, 1),
+                Token::Integer(13, 19, 1),
+                Token::Integer(20, 29, 1),
+                Token::Float64(30, 37, 1),
+                Token::Error(
+                    "Expect digits after radix prefix.",
+                    38,
+                    40,
+                    1,
+                    ErrorKind::Syntax
+                ),
+                Token::Error("Invalid numeric literal.", 41, 46, 1, ErrorKind::Syntax),
             ]
         );
     }
@@ -386,6 +863,45 @@ mod tests {
         test!("author", vec![Token::Identifier(0, 6, 1)]);
     }
 
+    #[test]
+    fn scan_unicode_identifiers() {
+        test!(
+            "作者 имя_2 ñame",
+            vec![
+                Token::Identifier(0, 6, 1),
+                Token::Identifier(7, 15, 1),
+                Token::Identifier(16, 21, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_identifier_punctuation() {
+        test!(
+            "lint:fix dev-deps a.b $x",
+            vec![
+                Token::Identifier(0, 8, 1),
+                Token::Identifier(9, 17, 1),
+                Token::Identifier(18, 21, 1),
+                Token::Error("Unexpected character.", 22, 23, 1, ErrorKind::Syntax),
+                Token::Identifier(23, 24, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_multibyte_string() {
+        test!(
+            r#"name "Кирилл ✓"; x"#,
+            vec![
+                Token::Identifier(0, 4, 1),
+                Token::String(6, 22, 1),
+                Token::Semicolon(23, 24, 1),
+                Token::Identifier(25, 26, 1),
+            ]
+        );
+    }
+
     #[test]
     fn skips_comments() {
         let source = r#"author //comment comment;
@@ -449,6 +965,184 @@ age;
         );
     }
 
+    #[test]
+    fn line_continuation() {
+        let source = "prod \"microbundle\" \\\n  \"-f es,cjs\";";
+        test!(
+            source,
+            vec![
+                Token::Identifier(0, 4, 1),
+                Token::String(6, 17, 1),
+                Token::String(24, 33, 2),
+                Token::Semicolon(34, 35, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn line_continuation_with_comment() {
+        test!(
+            "a \\ \t// more\r\n1",
+            vec![Token::Identifier(0, 1, 1), Token::Integer(14, 15, 2)]
+        );
+    }
+
+    #[test]
+    fn line_continuation_error() {
+        test!(
+            "a \\ b",
+            vec![
+                Token::Identifier(0, 1, 1),
+                Token::Error("Expect newline after '\\'.", 2, 4, 1, ErrorKind::Syntax),
+                Token::Identifier(4, 5, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_comment_styles() {
+        let mut scanner = Scanner::new("a # b\n// c");
+        scanner.configure(&ParserOptions {
+            comments: CommentStyles {
+                hash: false,
+                ..CommentStyles::default()
+            },
+            ..ParserOptions::default()
+        });
+
+        assert_eq!(
+            scanner.collect::<Vec<_>>(),
+            vec![
+                Token::Identifier(0, 1, 1),
+                Token::Error("Unexpected character.", 2, 3, 1, ErrorKind::Syntax),
+                Token::Identifier(4, 5, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn max_size() {
+        let mut scanner = Scanner::new("abc def");
+        scanner.configure(&ParserOptions {
+            max_size: Some(5),
+            ..ParserOptions::default()
+        });
+
+        assert_eq!(
+            scanner.collect::<Vec<_>>(),
+            vec![
+                Token::Identifier(0, 3, 1),
+                Token::Error(
+                    "Document exceeds maximum size.",
+                    5,
+                    5,
+                    1,
+                    ErrorKind::Limit(Limit::Size)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn max_string_length() {
+        let mut scanner = Scanner::new(r#""abc" "abcd" x "abcd"#);
+        scanner.configure(&ParserOptions {
+            max_string_length: Some(3),
+            ..ParserOptions::default()
+        });
+
+        assert_eq!(
+            scanner.collect::<Vec<_>>(),
+            vec![
+                Token::String(1, 4, 1),
+                Token::Error(
+                    "String exceeds maximum length.",
+                    6,
+                    12,
+                    1,
+                    ErrorKind::Limit(Limit::StringLength)
+                ),
+                Token::Identifier(13, 14, 1),
+                Token::Error(
+                    "String exceeds maximum length.",
+                    15,
+                    20,
+                    1,
+                    ErrorKind::Limit(Limit::StringLength)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn kept_comments() {
+        let scanner = Scanner::new("a; // one\n# two\nb \\ -- three\n 1;").keep_comments();
+
+        assert_eq!(
+            scanner.collect::<Vec<_>>(),
+            vec![
+                Token::Identifier(0, 1, 1),
+                Token::Semicolon(1, 2, 1),
+                Token::Comment(3, 9, 1),
+                Token::Comment(10, 15, 2),
+                Token::Identifier(16, 17, 3),
+                Token::Comment(20, 28, 3),
+                Token::Integer(30, 31, 4),
+                Token::Semicolon(31, 32, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn newline_terminators() {
+        let mut scanner = Scanner::new("a\r\nb;\n");
+        scanner.configure(&ParserOptions {
+            newline_terminators: true,
+            ..ParserOptions::default()
+        });
+
+        assert_eq!(
+            scanner.collect::<Vec<_>>(),
+            vec![
+                Token::Identifier(0, 1, 1),
+                Token::Semicolon(2, 3, 1),
+                Token::Identifier(3, 4, 2),
+                Token::Semicolon(4, 5, 2),
+                Token::Semicolon(5, 6, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn lone_dash() {
+        test!(
+            "offset -5 -x",
+            vec![
+                Token::Identifier(0, 6, 1),
+                Token::Integer(7, 9, 1),
+                Token::Error("Unexpected character.", 10, 11, 1, ErrorKind::Syntax),
+                Token::Identifier(11, 12, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn negative_numbers() {
+        test!(
+            "-5 -1.5 -.5 -2e-3 - -.x",
+            vec![
+                Token::Integer(0, 2, 1),
+                Token::Float64(3, 7, 1),
+                Token::Float64(8, 11, 1),
+                Token::Float64(12, 17, 1),
+                Token::Error("Unexpected character.", 18, 19, 1, ErrorKind::Syntax),
+                Token::Error("Unexpected character.", 20, 21, 1, ErrorKind::Syntax),
+                Token::Error("Unexpected character.", 21, 22, 1, ErrorKind::Syntax),
+                Token::Identifier(22, 23, 1),
+            ]
+        );
+    }
+
     #[test]
     fn scan_attribute() {
         test!(
@@ -490,7 +1184,7 @@ age;
         test!(
             "/a",
             vec![
-                Token::Error("Unexpected character.", 0, 1, 1),
+                Token::Error("Unexpected character.", 0, 1, 1, ErrorKind::Syntax),
                 Token::Identifier(1, 2, 1),
             ]
         );
@@ -0,0 +1,308 @@
+use std::cmp;
+use std::io::{self, BufRead};
+use std::iter::Peekable;
+use std::str::{self, CharIndices};
+
+/// A character and its byte offset from the start of the input.
+pub type Char = (usize, char);
+
+/// Characters fed to the `Scanner`.
+pub trait Source {
+    /// Consumes the next character.
+    fn next_char(&mut self) -> Option<Char>;
+
+    /// Returns the next character without consuming it.
+    fn peek_char(&mut self) -> Option<Char>;
+
+    /// Byte offset just past the last consumed character.
+    fn position(&self) -> usize;
+
+    /// Text between two byte offsets. Only text at or after the last
+    /// `discard` offset is guaranteed to be available.
+    fn slice(&self, start: usize, end: usize) -> &str;
+
+    /// Tells the source that text before `offset` is no longer needed.
+    fn discard(&mut self, _offset: usize) {}
+
+    /// Returns the reason input ended early, once.
+    fn take_error(&mut self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// In-memory source over a string slice.
+pub struct StrSource<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    position: usize,
+}
+
+impl<'a> StrSource<'a> {
+    pub fn new(source: &'a str) -> Self {
+        StrSource {
+            source,
+            chars: source.char_indices().peekable(),
+            position: 0,
+        }
+    }
+}
+
+impl<'a> Source for StrSource<'a> {
+    fn next_char(&mut self) -> Option<Char> {
+        let next = self.chars.next();
+        if let Some((index, ch)) = next {
+            self.position = index + ch.len_utf8();
+        }
+        next
+    }
+
+    fn peek_char(&mut self) -> Option<Char> {
+        self.chars.peek().copied()
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn slice(&self, start: usize, end: usize) -> &str {
+        &self.source[start..end]
+    }
+}
+
+/// Incremental source over a `BufRead`.
+///
+/// Only the text of the token being scanned is kept in memory, along with
+/// at most one chunk of the reader's buffer. UTF-8 sequences split between
+/// two reads are reassembled before decoding.
+pub struct ReadSource<R> {
+    reader: R,
+    // Decoded text starting at byte offset `text_start` of the input.
+    text: String,
+    text_start: usize,
+    // Index into `text` of the next character to consume.
+    next: usize,
+    // Absolute offset before which text can be dropped.
+    keep: usize,
+    // Bytes of a UTF-8 sequence cut off at the end of the last read.
+    pending: Vec<u8>,
+    done: bool,
+    error: Option<io::Error>,
+    reported: bool,
+}
+
+impl<R: BufRead> ReadSource<R> {
+    pub fn new(reader: R) -> Self {
+        ReadSource {
+            reader,
+            text: String::new(),
+            text_start: 0,
+            next: 0,
+            keep: 0,
+            pending: Vec::new(),
+            done: false,
+            error: None,
+            reported: false,
+        }
+    }
+
+    /// The error that stopped reading, if any.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    fn compact(&mut self) {
+        let cut = cmp::min(self.keep.saturating_sub(self.text_start), self.next);
+        self.text.drain(..cut);
+        self.text_start += cut;
+        self.next -= cut;
+    }
+
+    fn decode(&mut self) {
+        match str::from_utf8(&self.pending) {
+            Ok(text) => {
+                self.text.push_str(text);
+                self.pending.clear();
+            }
+            Err(error) => {
+                let valid = error.valid_up_to();
+                // `valid_up_to` always ends on a character boundary.
+                self.text
+                    .push_str(str::from_utf8(&self.pending[..valid]).unwrap_or(""));
+
+                if error.error_len().is_some() {
+                    self.fail(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "stream did not contain valid UTF-8",
+                    ));
+                } else {
+                    self.pending.drain(..valid);
+                }
+            }
+        }
+    }
+
+    fn fail(&mut self, error: io::Error) {
+        self.error = Some(error);
+        self.pending.clear();
+        self.done = true;
+    }
+
+    // Reads until a character is available at `next`. Returns false at the
+    // end of input.
+    fn fill(&mut self) -> bool {
+        while self.next >= self.text.len() {
+            if self.done {
+                return false;
+            }
+
+            self.compact();
+
+            match self.reader.fill_buf() {
+                Ok([]) => {
+                    if self.pending.is_empty() {
+                        self.done = true;
+                    } else {
+                        self.fail(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "stream ended inside a UTF-8 sequence",
+                        ));
+                    }
+                }
+                Ok(bytes) => {
+                    let read = bytes.len();
+                    self.pending.extend_from_slice(bytes);
+                    self.reader.consume(read);
+                    self.decode();
+                }
+                Err(ref error) if error.kind() == io::ErrorKind::Interrupted => (),
+                Err(error) => self.fail(error),
+            }
+        }
+
+        true
+    }
+
+    fn char_at_next(&self) -> Option<Char> {
+        self.text[self.next..]
+            .chars()
+            .next()
+            .map(|ch| (self.text_start + self.next, ch))
+    }
+}
+
+impl<R: BufRead> Source for ReadSource<R> {
+    fn next_char(&mut self) -> Option<Char> {
+        if !self.fill() {
+            return None;
+        }
+
+        let next = self.char_at_next();
+        if let Some((_, ch)) = next {
+            self.next += ch.len_utf8();
+        }
+        next
+    }
+
+    fn peek_char(&mut self) -> Option<Char> {
+        if !self.fill() {
+            return None;
+        }
+
+        self.char_at_next()
+    }
+
+    fn position(&self) -> usize {
+        self.text_start + self.next
+    }
+
+    fn slice(&self, start: usize, end: usize) -> &str {
+        &self.text[start - self.text_start..end - self.text_start]
+    }
+
+    fn discard(&mut self, offset: usize) {
+        self.keep = offset;
+    }
+
+    fn take_error(&mut self) -> Option<&'static str> {
+        if self.reported {
+            return None;
+        }
+
+        let msg = match self.error {
+            Some(ref error) if error.kind() == io::ErrorKind::InvalidData => "Invalid UTF-8.",
+            Some(_) => "Failed to read input.",
+            None => return None,
+        };
+
+        self.reported = true;
+        Some(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ErrorKind;
+    use crate::scanner::{Scanner, Token};
+    use std::io::BufReader;
+
+    fn scan_reader(source: &[u8], capacity: usize) -> Vec<Token> {
+        Scanner::from_reader(BufReader::with_capacity(capacity, source)).collect()
+    }
+
+    #[test]
+    fn same_tokens_as_in_memory() {
+        let source =
+            "author \"Кирилл\";\nscripts {\n  lint:fix \"eslint ✓\"; // ok\n  n 1.5e3 -- x\n}\n";
+        let expected: Vec<Token> = Scanner::new(source).collect();
+
+        for capacity in 1..8 {
+            assert_eq!(scan_reader(source.as_bytes(), capacity), expected);
+        }
+    }
+
+    #[test]
+    fn slices_current_token() {
+        let mut scanner = Scanner::from_reader(BufReader::with_capacity(1, "ab \"ü\"".as_bytes()));
+
+        assert_eq!(scanner.next(), Some(Token::Identifier(0, 2, 1)));
+        assert_eq!(scanner.source_slice(0, 2), "ab");
+        assert_eq!(scanner.next(), Some(Token::String(4, 6, 1)));
+        assert_eq!(scanner.source_slice(4, 6), "ü");
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        assert_eq!(
+            scan_reader(b"ab \xff", 2),
+            vec![
+                Token::Identifier(0, 2, 1),
+                Token::Error("Invalid UTF-8.", 3, 3, 1, ErrorKind::Input),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_utf8() {
+        assert_eq!(
+            scan_reader(b"ab \xd0", 4),
+            vec![
+                Token::Identifier(0, 2, 1),
+                Token::Error("Invalid UTF-8.", 3, 3, 1, ErrorKind::Input),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_bounded_window() {
+        let input = "abc ".repeat(10_000);
+        let mut source = ReadSource::new(BufReader::with_capacity(16, input.as_bytes()));
+
+        while let Some((index, _)) = source.next_char() {
+            source.discard(index);
+            assert!(source.text.len() <= 32);
+        }
+
+        assert_eq!(source.position(), input.len());
+    }
+}
@@ -0,0 +1,493 @@
+//! Traversal of tag trees.
+//!
+//! Tags are identified by their path: the names of the tag and its
+//! ancestors joined with `/`, like `scripts/build`. None of the traversals
+//! recurse, so they work on trees of any depth.
+
+use crate::parser::{Tag, Value};
+use std::collections::VecDeque;
+
+/// Iterates over `tags` and their descendants, parents before children.
+pub fn depth_first(tags: &[Tag]) -> DepthFirst<'_> {
+    DepthFirst {
+        stack: tags.iter().rev().map(|tag| (String::new(), tag)).collect(),
+    }
+}
+
+/// Iterates over `tags` and their descendants, level by level.
+pub fn breadth_first(tags: &[Tag]) -> BreadthFirst<'_> {
+    BreadthFirst {
+        queue: tags.iter().map(|tag| (String::new(), tag)).collect(),
+    }
+}
+
+/// Finds the tags at `path`, in document order. Every tag whose name matches
+/// a segment is followed, so `server/port` finds the `port` children of all
+/// `server` tags.
+pub fn find<'a>(tags: &'a [Tag], path: &str) -> Vec<&'a Tag> {
+    let mut found: Vec<&Tag> = Vec::new();
+    let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+
+    if let Some(first) = segments.next() {
+        found = tags.iter().filter(|tag| tag.name() == first).collect();
+    }
+    for segment in segments {
+        found = found
+            .into_iter()
+            .flat_map(|tag| tag.children().iter())
+            .filter(|tag| tag.name() == segment)
+            .collect();
+    }
+
+    found
+}
+
+fn join(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        String::from(name)
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+pub struct DepthFirst<'a> {
+    // Tags still to visit, with the path of their parent.
+    stack: Vec<(String, &'a Tag)>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = (String, &'a Tag);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (parent, tag) = self.stack.pop()?;
+        let path = join(&parent, tag.name());
+
+        for child in tag.children().iter().rev() {
+            self.stack.push((path.clone(), child));
+        }
+
+        Some((path, tag))
+    }
+}
+
+pub struct BreadthFirst<'a> {
+    // Tags still to visit, with the path of their parent.
+    queue: VecDeque<(String, &'a Tag)>,
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = (String, &'a Tag);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (parent, tag) = self.queue.pop_front()?;
+        let path = join(&parent, tag.name());
+
+        for child in tag.children() {
+            self.queue.push_back((path.clone(), child));
+        }
+
+        Some((path, tag))
+    }
+}
+
+/// Hooks called by `walk`. A tag is entered, then its values are visited in
+/// order and its attributes in name order, then its children are walked,
+/// and finally the tag is left.
+pub trait Visitor {
+    fn enter_tag(&mut self, _path: &str, _tag: &Tag) {}
+
+    fn leave_tag(&mut self, _path: &str, _tag: &Tag) {}
+
+    fn visit_value(&mut self, _path: &str, _value: &Value) {}
+
+    fn visit_attribute(&mut self, _path: &str, _name: &str, _value: &Value) {}
+}
+
+/// Hooks called by `walk_mut`, in the same order as those of `Visitor`.
+/// Changes made to the children of a tag on entering it are walked.
+pub trait VisitorMut {
+    fn enter_tag(&mut self, _path: &str, _tag: &mut Tag) {}
+
+    fn leave_tag(&mut self, _path: &str, _tag: &mut Tag) {}
+
+    fn visit_value(&mut self, _path: &str, _value: &mut Value) {}
+
+    fn visit_attribute(&mut self, _path: &str, _name: &str, _value: &mut Value) {}
+}
+
+/// Transforms a tree by value, with `fold`. Each method receives a part of
+/// the tree and returns what replaces it.
+pub trait Fold {
+    fn fold_value(&mut self, _path: &str, value: Value) -> Value {
+        value
+    }
+
+    /// Returning `None` removes the attribute.
+    fn fold_attribute(
+        &mut self,
+        _path: &str,
+        name: String,
+        value: Value,
+    ) -> Option<(String, Value)> {
+        Some((name, value))
+    }
+
+    /// Called once the values, attributes and children of the tag have been
+    /// folded. Returning `None` removes the tag.
+    fn fold_tag(&mut self, _path: &str, tag: Tag) -> Option<Tag> {
+        Some(tag)
+    }
+}
+
+enum Step<'a> {
+    Enter(&'a Tag),
+    Leave(&'a Tag),
+}
+
+/// Walks `tags` and their descendants, calling the hooks of `visitor`.
+pub fn walk<V: Visitor>(tags: &[Tag], visitor: &mut V) {
+    let mut steps: Vec<Step> = tags.iter().rev().map(Step::Enter).collect();
+    // Length of `path` before each open tag was entered.
+    let mut lengths = Vec::new();
+    let mut path = String::new();
+
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Enter(tag) => {
+                lengths.push(path.len());
+                path = join(&path, tag.name());
+
+                visitor.enter_tag(&path, tag);
+                for value in tag.values() {
+                    visitor.visit_value(&path, value);
+                }
+                for (name, value) in sorted(tag) {
+                    visitor.visit_attribute(&path, name, value);
+                }
+
+                steps.push(Step::Leave(tag));
+                steps.extend(tag.children().iter().rev().map(Step::Enter));
+            }
+            Step::Leave(tag) => {
+                visitor.leave_tag(&path, tag);
+                path.truncate(lengths.pop().unwrap_or(0));
+            }
+        }
+    }
+}
+
+fn sorted(tag: &Tag) -> Vec<(&String, &Value)> {
+    let mut attributes: Vec<_> = tag.attributes().iter().collect();
+    attributes.sort_by(|a, b| a.0.cmp(b.0));
+    attributes
+}
+
+/// Walks `tags` and their descendants, calling the hooks of `visitor`.
+pub fn walk_mut<V: VisitorMut>(tags: &mut Vec<Tag>, visitor: &mut V) {
+    *tags = rebuild(std::mem::take(tags), &mut MutHooks(visitor));
+}
+
+/// Folds `tags` and their descendants with `folder`, children first.
+pub fn fold<F: Fold>(tags: Vec<Tag>, folder: &mut F) -> Vec<Tag> {
+    rebuild(tags, &mut FoldHooks(folder))
+}
+
+// What `rebuild` does with each tag.
+trait Hooks {
+    fn enter(&mut self, path: &str, tag: &mut Tag);
+
+    fn leave(&mut self, path: &str, tag: Tag) -> Option<Tag>;
+}
+
+struct MutHooks<'a, V>(&'a mut V);
+
+impl<'a, V: VisitorMut> Hooks for MutHooks<'a, V> {
+    fn enter(&mut self, path: &str, tag: &mut Tag) {
+        self.0.enter_tag(path, tag);
+        for value in tag.values_mut() {
+            self.0.visit_value(path, value);
+        }
+
+        let mut names: Vec<_> = tag.attributes().keys().cloned().collect();
+        names.sort();
+        for name in names {
+            if let Some(value) = tag.attribute_mut(&name) {
+                self.0.visit_attribute(path, &name, value);
+            }
+        }
+    }
+
+    fn leave(&mut self, path: &str, mut tag: Tag) -> Option<Tag> {
+        self.0.leave_tag(path, &mut tag);
+        Some(tag)
+    }
+}
+
+struct FoldHooks<'a, F>(&'a mut F);
+
+impl<'a, F: Fold> Hooks for FoldHooks<'a, F> {
+    fn enter(&mut self, path: &str, tag: &mut Tag) {
+        let values = std::mem::take(tag.values_mut());
+        *tag.values_mut() = values
+            .into_iter()
+            .map(|value| self.0.fold_value(path, value))
+            .collect();
+
+        let mut attributes: Vec<_> = tag.attributes.drain().collect();
+        attributes.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, value) in attributes {
+            if let Some((name, value)) = self.0.fold_attribute(path, name, value) {
+                tag.set_attribute(name, value);
+            }
+        }
+    }
+
+    fn leave(&mut self, path: &str, tag: Tag) -> Option<Tag> {
+        self.0.fold_tag(path, tag)
+    }
+}
+
+// A tag whose children are being rebuilt.
+struct Frame {
+    tag: Tag,
+    path: String,
+    pending: std::vec::IntoIter<Tag>,
+    done: Vec<Tag>,
+}
+
+// Moves every tag out of the tree and back in, calling `hooks` on the way
+// down and on the way up. Children are taken out of their parent while
+// they are rebuilt, so that no recursion or aliasing is needed.
+fn rebuild<H: Hooks>(tags: Vec<Tag>, hooks: &mut H) -> Vec<Tag> {
+    let mut root = Frame {
+        tag: Tag::new(String::new()),
+        path: String::new(),
+        pending: tags.into_iter(),
+        done: Vec::new(),
+    };
+    let mut stack: Vec<Frame> = Vec::new();
+
+    loop {
+        let frame = stack.last_mut().unwrap_or(&mut root);
+
+        match frame.pending.next() {
+            Some(mut tag) => {
+                let path = join(&frame.path, tag.name());
+                hooks.enter(&path, &mut tag);
+                let pending = std::mem::take(tag.children_mut()).into_iter();
+
+                stack.push(Frame {
+                    tag,
+                    path,
+                    pending,
+                    done: Vec::new(),
+                });
+            }
+            None => match stack.pop() {
+                Some(mut frame) => {
+                    *frame.tag.children_mut() = frame.done;
+                    let parent = stack.last_mut().unwrap_or(&mut root);
+
+                    if let Some(tag) = hooks.leave(&frame.path, frame.tag) {
+                        parent.done.push(tag);
+                    }
+                }
+                None => return root.done,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::writer::write;
+
+    const SOURCE: &str = "a 1 { b x=1 { d; } c; }\ne;";
+
+    fn parse(source: &str) -> Vec<Tag> {
+        Parser::from_str(source).parse().unwrap()
+    }
+
+    #[test]
+    fn iterators() {
+        let tags = parse(SOURCE);
+        let paths = |iter: &mut dyn Iterator<Item = (String, &Tag)>| -> Vec<String> {
+            iter.map(|(path, _)| path).collect()
+        };
+
+        assert_eq!(
+            paths(&mut depth_first(&tags)),
+            vec!["a", "a/b", "a/b/d", "a/c", "e"]
+        );
+        assert_eq!(
+            paths(&mut breadth_first(&tags)),
+            vec!["a", "e", "a/b", "a/c", "a/b/d"]
+        );
+    }
+
+    #[test]
+    fn finds_paths() {
+        let tags = parse("a { b 1; b 2 { d; } }\na { b 3; }\nb 4;");
+        let values = |path: &str| -> Vec<String> {
+            find(&tags, path)
+                .iter()
+                .map(|tag| write(&[(*tag).clone()]).unwrap())
+                .collect()
+        };
+
+        assert_eq!(values("a/b"), vec!["b 1;\n", "b 2 {\n  d;\n}\n", "b 3;\n"]);
+        assert_eq!(values("/b/"), vec!["b 4;\n"]);
+        assert_eq!(values("a/b/d"), vec!["d;\n"]);
+        assert!(values("a/c").is_empty());
+        assert!(values("").is_empty());
+    }
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl Visitor for Log {
+        fn enter_tag(&mut self, path: &str, _tag: &Tag) {
+            self.0.push(format!("enter {}", path));
+        }
+
+        fn leave_tag(&mut self, path: &str, _tag: &Tag) {
+            self.0.push(format!("leave {}", path));
+        }
+
+        fn visit_value(&mut self, path: &str, value: &Value) {
+            self.0.push(format!("value {} {}", path, value));
+        }
+
+        fn visit_attribute(&mut self, path: &str, name: &str, value: &Value) {
+            self.0
+                .push(format!("attribute {} {}={}", path, name, value));
+        }
+    }
+
+    #[test]
+    fn visitor() {
+        let mut log = Log::default();
+        walk(&parse(SOURCE), &mut log);
+
+        assert_eq!(
+            log.0,
+            vec![
+                "enter a",
+                "value a 1",
+                "enter a/b",
+                "attribute a/b x=1",
+                "enter a/b/d",
+                "leave a/b/d",
+                "leave a/b",
+                "enter a/c",
+                "leave a/c",
+                "leave a",
+                "enter e",
+                "leave e",
+            ]
+        );
+    }
+
+    struct Bump;
+
+    impl VisitorMut for Bump {
+        fn enter_tag(&mut self, _path: &str, tag: &mut Tag) {
+            tag.retain_children(|child| child.name() != "c");
+        }
+
+        fn leave_tag(&mut self, path: &str, tag: &mut Tag) {
+            if path == "a/b/d" {
+                tag.push_value(Value::Null);
+            }
+        }
+
+        fn visit_value(&mut self, _path: &str, value: &mut Value) {
+            *value = Value::from(2);
+        }
+
+        fn visit_attribute(&mut self, _path: &str, _name: &str, value: &mut Value) {
+            *value = Value::from(3);
+        }
+    }
+
+    #[test]
+    fn visitor_mut() {
+        let mut tags = parse(SOURCE);
+        walk_mut(&mut tags, &mut Bump);
+
+        assert_eq!(
+            write(&tags).unwrap(),
+            "a 2 {\n  b x=3 {\n    d null;\n  }\n}\ne;\n"
+        );
+    }
+
+    struct Flatten;
+
+    impl Fold for Flatten {
+        fn fold_attribute(
+            &mut self,
+            _path: &str,
+            name: String,
+            value: Value,
+        ) -> Option<(String, Value)> {
+            Some((name.to_uppercase(), value))
+        }
+
+        fn fold_tag(&mut self, path: &str, tag: Tag) -> Option<Tag> {
+            if path == "e" {
+                None
+            } else {
+                Some(tag)
+            }
+        }
+    }
+
+    #[test]
+    fn folds() {
+        let tags = fold(parse(SOURCE), &mut Flatten);
+
+        assert_eq!(
+            write(&tags).unwrap(),
+            "a 1 {\n  b X=1 {\n    d;\n  }\n  c;\n}\n"
+        );
+    }
+
+    struct Count(usize);
+
+    impl Visitor for Count {
+        fn enter_tag(&mut self, _path: &str, _tag: &Tag) {
+            self.0 += 1;
+        }
+
+        fn leave_tag(&mut self, _path: &str, _tag: &Tag) {
+            self.0 += 1;
+        }
+    }
+
+    #[test]
+    fn deep_trees() {
+        // Every path repeats the names of its ancestors, so the depth is
+        // kept small enough for the paths of a single chain to stay cheap.
+        let depth = 10_000;
+        let source = format!("{}{}", "a{".repeat(depth), "}".repeat(depth));
+        let options = crate::options::ParserOptions {
+            max_depth: None,
+            ..Default::default()
+        };
+        let tags = Parser::with_options(crate::scanner::Scanner::new(&source), options)
+            .parse()
+            .unwrap();
+
+        assert_eq!(depth_first(&tags).count(), depth);
+        assert_eq!(breadth_first(&tags).count(), depth);
+
+        let mut count = Count(0);
+        walk(&tags, &mut count);
+        assert_eq!(count.0, 2 * depth);
+
+        let tags = fold(tags, &mut Flatten);
+        assert_eq!(depth_first(&tags).count(), depth);
+    }
+}
@@ -0,0 +1,316 @@
+use crate::parser::{Radix, Tag, Value};
+use crate::scanner::{Scanner, Token};
+use std::fmt::Write;
+use std::{error, fmt};
+
+const INDENT: &str = "  ";
+
+/// A name or value that SDL text cannot represent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteError {
+    /// A tag or attribute name that would not be read back as an
+    /// identifier, such as an empty name.
+    Name(String),
+    /// A string containing `"`. SDL strings have no escapes.
+    String(String),
+    /// A float with no SDL literal, which is `-inf`.
+    Float(f64),
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriteError::Name(name) => write!(f, "`{}` is not a valid SDL name", name),
+            WriteError::String(v) => write!(f, "string {:?} contains '\"'", v),
+            WriteError::Float(v) => write!(f, "{} cannot be written as SDL", v),
+        }
+    }
+}
+
+impl error::Error for WriteError {}
+
+/// Writes tags as SDL text, one tag per line.
+///
+/// Tags without values or attributes are written as flags (`enabled;`),
+/// attributes are written in name order, and integers keep the radix they
+/// were read in. `nan` and `inf` are read back only with
+/// `ParserOptions::float_keywords`, and non-decimal integers only with
+/// `ParserOptions::extensions`. A `Value::Long` is written as a plain
+/// integer literal: outside the 32 bit range it is read back only with
+/// `IntegerOverflow::Widen`, and inside it is read back as a
+/// `Value::Integer`.
+pub fn write(tags: &[Tag]) -> Result<String, WriteError> {
+    let mut out = String::new();
+    // Tags still to write, and the depths of the bodies to close, kept on an
+    // explicit stack like the tags of `Parser`.
+    let mut stack: Vec<Step> = tags.iter().rev().map(|tag| Step::Tag(tag, 0)).collect();
+
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Tag(tag, depth) => {
+                write_tag(&mut out, tag, depth)?;
+
+                if !tag.children.is_empty() {
+                    stack.push(Step::Close(depth));
+                    stack.extend(
+                        tag.children
+                            .iter()
+                            .rev()
+                            .map(|child| Step::Tag(child, depth + 1)),
+                    );
+                }
+            }
+            Step::Close(depth) => {
+                out.push_str(&INDENT.repeat(depth));
+                out.push_str("}\n");
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Writes a single value as an SDL literal.
+pub fn write_value(value: &Value) -> Result<String, WriteError> {
+    match value {
+        Value::String(v) if v.contains('"') => Err(WriteError::String(v.clone())),
+        Value::Float(v) if *v == f64::NEG_INFINITY => Err(WriteError::Float(*v)),
+        _ => Ok(literal(value)),
+    }
+}
+
+/// Checks that `name` is read back as a single identifier.
+pub(crate) fn check_name(name: &str) -> Result<(), WriteError> {
+    let tokens: Vec<_> = Scanner::new(name).take(2).collect();
+
+    if tokens == [Token::Identifier(0, name.len(), 1)] {
+        Ok(())
+    } else {
+        Err(WriteError::Name(String::from(name)))
+    }
+}
+
+// `value` as SDL, whether or not it can be read back.
+pub(crate) fn literal(value: &Value) -> String {
+    match value {
+        Value::String(v) => format!("\"{}\"", v),
+        Value::Integer(v, radix) => integer(i64::from(*v), *radix),
+        Value::Long(v, radix) => integer(*v, *radix),
+        Value::Float(v) if v.is_nan() => String::from("nan"),
+        Value::Float(v) if v.is_infinite() => String::from(if *v > 0.0 { "inf" } else { "-inf" }),
+        // `Debug` always includes a '.' or an exponent, so the literal is
+        // not read back as an integer.
+        Value::Float(v) => format!("{:?}", v),
+        Value::Boolean(v) => format!("{}", v),
+        Value::Null => String::from("null"),
+    }
+}
+
+fn integer(value: i64, radix: Radix) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let magnitude = value.unsigned_abs();
+
+    match radix {
+        Radix::Binary => format!("{}0b{:b}", sign, magnitude),
+        Radix::Octal => format!("{}0o{:o}", sign, magnitude),
+        Radix::Decimal => format!("{}", value),
+        Radix::Hexadecimal => format!("{}0x{:X}", sign, magnitude),
+    }
+}
+
+enum Step<'a> {
+    Tag(&'a Tag, usize),
+    Close(usize),
+}
+
+// Writes `tag` up to its `;`, or up to the `{` opening its body.
+fn write_tag(out: &mut String, tag: &Tag, depth: usize) -> Result<(), WriteError> {
+    check_name(&tag.name)?;
+    out.push_str(&INDENT.repeat(depth));
+    out.push_str(&tag.name);
+
+    for value in &tag.values {
+        let _ = write!(out, " {}", write_value(value)?);
+    }
+
+    let mut attributes: Vec<_> = tag.attributes.iter().collect();
+    attributes.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (name, value) in attributes {
+        check_name(name)?;
+        let _ = write!(out, " {}={}", name, write_value(value)?);
+    }
+
+    out.push_str(if tag.children.is_empty() {
+        ";\n"
+    } else {
+        " {\n"
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::{IntegerOverflow, ParserOptions};
+    use crate::parser::Parser;
+
+    fn read(source: &str) -> Vec<Tag> {
+        let options = ParserOptions {
+            extensions: true,
+            float_keywords: true,
+            integer_overflow: IntegerOverflow::Widen,
+            ..ParserOptions::default()
+        };
+        Parser::with_options(Scanner::new(source), options)
+            .parse()
+            .unwrap()
+    }
+
+    fn rewrite(source: &str) -> String {
+        write(&read(source)).unwrap()
+    }
+
+    #[test]
+    fn writes_nested_tags() {
+        assert_eq!(
+            rewrite("scripts { build { prod \"microbundle\"; } format p=true \"prettier\"; }"),
+            "scripts {\n  build {\n    prod \"microbundle\";\n  }\n  format \"prettier\" p=true;\n}\n"
+        );
+    }
+
+    #[test]
+    fn writes_flags() {
+        assert_eq!(rewrite("enabled; section {}"), "enabled;\nsection;\n");
+    }
+
+    #[test]
+    fn keeps_radix() {
+        assert_eq!(
+            rewrite("regs 0xFF00 0o755 0b1010 1_000_000;"),
+            "regs 0xFF00 0o755 0b1010 1000000;\n"
+        );
+    }
+
+    #[test]
+    fn writes_floats_as_floats() {
+        assert_eq!(
+            rewrite("f 1.0 2.5e3 1e100 nan inf;"),
+            "f 1.0 2500.0 1e100 nan inf;\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_values() {
+        let mut tag = Tag::new(String::from("dev-deps"));
+        tag.values = vec![
+            Value::Integer(-5, Radix::Decimal),
+            Value::Integer(i32::MIN, Radix::Decimal),
+            Value::Integer(-255, Radix::Hexadecimal),
+            Value::Long(i64::MIN, Radix::Octal),
+            Value::Long(4_294_967_296, Radix::Binary),
+            Value::Float(-1.5),
+            Value::Float(1e-300),
+            Value::Float(f64::INFINITY),
+            Value::String(String::from("two\nlines // not a comment")),
+            Value::Boolean(false),
+            Value::Null,
+        ];
+        tag.attributes
+            .insert(String::from("offset"), Value::Float(-0.25));
+        tag.attributes
+            .insert(String::from("ns:key"), Value::Integer(-1, Radix::Binary));
+
+        let written = write(std::slice::from_ref(&tag)).unwrap();
+
+        let read = read(&written);
+
+        assert_eq!(read[0].name, tag.name);
+        assert_eq!(read[0].values, tag.values);
+        assert_eq!(read[0].attributes, tag.attributes);
+    }
+
+    #[test]
+    fn round_trips_with_default_options() {
+        let mut tag = Tag::new(String::from("limits"));
+        tag.values = vec![
+            Value::Integer(i32::MIN, Radix::Decimal),
+            Value::Float(-1.5),
+            Value::String(String::from("text")),
+            Value::Boolean(true),
+            Value::Null,
+        ];
+        tag.attributes.insert(
+            String::from("max"),
+            Value::Integer(i32::MAX, Radix::Decimal),
+        );
+
+        let written = write(std::slice::from_ref(&tag)).unwrap();
+        let tags = Parser::new(Scanner::new(&written)).parse().unwrap();
+
+        assert_eq!(tags[0].values, tag.values);
+        assert_eq!(tags[0].attributes, tag.attributes);
+
+        let mut long = Tag::new(String::from("size"));
+        long.values = vec![Value::Long(4_294_967_296, Radix::Decimal)];
+
+        let written = write(std::slice::from_ref(&long)).unwrap();
+        let error = Parser::new(Scanner::new(&written)).parse().unwrap_err();
+
+        assert_eq!(error.0, "Integer literal out of range.");
+        assert_eq!(read(&written)[0].values, long.values);
+    }
+
+    #[test]
+    fn rejects_unrepresentable() {
+        let quoted = Value::String(String::from("say \"hi\""));
+        assert_eq!(
+            write_value(&quoted),
+            Err(WriteError::String(String::from("say \"hi\"")))
+        );
+        assert_eq!(
+            write_value(&Value::Float(f64::NEG_INFINITY)),
+            Err(WriteError::Float(f64::NEG_INFINITY))
+        );
+
+        for name in ["", "a b", "1a", "true", "a;"] {
+            assert_eq!(
+                write(&[Tag::new(String::from(name))]),
+                Err(WriteError::Name(String::from(name)))
+            );
+        }
+
+        let mut tag = Tag::new(String::from("a"));
+        tag.attributes.insert(String::from("k="), Value::Null);
+        assert_eq!(write(&[tag]), Err(WriteError::Name(String::from("k="))));
+    }
+
+    #[test]
+    fn writes_deep_trees() {
+        let mut tag = Tag::new(String::from("a"));
+        for _ in 1..2_000 {
+            let mut parent = Tag::new(String::from("a"));
+            parent.children.push(tag);
+            tag = parent;
+        }
+
+        // Indentation makes the text quadratic in the depth, so a small
+        // stack stands in for a deeper tree.
+        let written = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || write(&[tag]).unwrap())
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(written.starts_with("a {\n  a {\n"));
+        assert!(written.ends_with("  }\n}\n"));
+    }
+
+    #[test]
+    fn reads_back_config() {
+        let source = std::fs::read_to_string("config.sdl").unwrap();
+        let written = rewrite(&source);
+
+        assert_eq!(rewrite(&written), written);
+    }
+}
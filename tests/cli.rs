@@ -0,0 +1,213 @@
+// Runs the `sdl` binary on small documents and checks its output and exit
+// code.
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn sdl(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sdl"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+// A file in the target directory, unique to the calling test.
+fn file(name: &str, contents: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn check() {
+    let output = sdl(&["check", "-"], "a 1;\nb { c; }\n");
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = sdl(&["check", "-"], "a 1;\nb =;\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("-: Syntax error at line 2"));
+
+    let output = sdl(&["check", "--json", "-"], "a 1;\nb =;\n");
+    assert_eq!(
+        stdout(&output),
+        "{\"file\": \"-\", \"severity\": \"error\", \"line\": 2, \"start\": 7, \"end\": 8, \"message\": \"Expect literal value or attribute.\"}\n"
+    );
+
+    let output = sdl(&["check", "--json", "-"], "a b=1 2;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "{\"file\": \"-\", \"severity\": \"warning\", \"line\": 1, \"start\": 6, \"end\": 7, \"message\": \"Values must come before attributes.\"}\n"
+    );
+}
+
+#[test]
+fn check_unreadable_files() {
+    let path = file("check.sdl", "a =;\n");
+    let path = path.to_str().unwrap();
+    let missing = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("missing.sdl");
+    let missing = missing.to_str().unwrap();
+
+    let output = sdl(&["check", "--json", missing, path], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).contains("\"severity\": \"error\""));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).ends_with("sdl: 1 of 2 files could not be read\n")
+    );
+}
+
+#[test]
+fn fmt() {
+    let output = sdl(&["fmt", "-"], "a   1 ;b{c;}");
+    assert_eq!(stdout(&output), "a 1;\nb {\n  c;\n}\n");
+
+    let path = file("fmt.sdl", "a   1 ;");
+    let path = path.to_str().unwrap();
+
+    let output = sdl(&["fmt", "--check", path], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), format!("{}\n", path));
+
+    assert_eq!(sdl(&["fmt", path], "").status.code(), Some(0));
+    assert_eq!(fs::read_to_string(path).unwrap(), "a 1;\n");
+
+    let output = sdl(&["fmt", "--check", "--json", path], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        format!("{{\"file\": \"{}\", \"formatted\": true}}\n", path)
+    );
+}
+
+#[test]
+fn get() {
+    let source = "server port=80 { name \"a\" \"b\"; }\nserver port=81;";
+
+    let output = sdl(&["get", "server@port", "-"], source);
+    assert_eq!(stdout(&output), "80\n81\n");
+
+    let output = sdl(&["get", "--json", "server/name", "-"], source);
+    assert_eq!(stdout(&output), "[\"a\", \"b\"]\n");
+
+    let output = sdl(&["get", "server/missing", "-"], source);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn convert() {
+    let output = sdl(&["convert", "--to", "json", "-"], "a 1;");
+    assert_eq!(
+        stdout(&output),
+        "[\n  {\n    \"name\": \"a\",\n    \"values\": [1]\n  }\n]\n"
+    );
+
+    let output = sdl(&["convert", "--to", "sdl", "-"], "a   1 // one\n;");
+    assert_eq!(stdout(&output), "a 1;\n");
+
+    let output = sdl(&["convert", "--to", "json", "--friendly", "-"], "a 1;");
+    assert_eq!(stdout(&output), "{\n  \"a\": 1\n}\n");
+
+    let output = sdl(
+        &[
+            "convert",
+            "--from",
+            "json",
+            "--to",
+            "sdl",
+            "--friendly",
+            "-",
+        ],
+        "{\"a\": {\"@k\": true, \"b\": [1, 2]}}",
+    );
+    assert_eq!(stdout(&output), "a k=true {\n  b 1;\n  b 2;\n}\n");
+
+    let path = file("convert.json", "[{\"name\": \"a\", \"values\": [1]}]");
+    let output = sdl(&["convert", "--to", "sdl", path.to_str().unwrap()], "");
+    assert_eq!(stdout(&output), "a 1;\n");
+
+    let output = sdl(
+        &["convert", "--from", "json", "--to", "sdl", "-"],
+        "[{\"nam\": 1}]",
+    );
+    assert_eq!(output.status.code(), Some(2));
+
+    // Anonymous tags have no SDL form.
+    let output = sdl(&["convert", "--from", "json", "--to", "sdl", "-"], "[{}]");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`` is not a valid SDL name"));
+
+    let output = sdl(&["convert", "--to", "xml", "-"], "a 1;");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn edits() {
+    let source = "// manifest\nversion \"4.0.3\"; // bumped\nserver port=80 {\n  tls;\n}\n";
+
+    let output = sdl(&["set", "version", "-", "4.0.4"], source);
+    assert_eq!(stdout(&output), source.replace("4.0.3", "4.0.4"));
+
+    let output = sdl(&["set", "server@port", "-", "8080"], source);
+    assert_eq!(stdout(&output), source.replace("80", "8080"));
+
+    let output = sdl(&["set", "server@port", "-", "3000000000"], source);
+    assert_eq!(stdout(&output), source.replace("80", "3000000000"));
+
+    let output = sdl(&["set", "version", "-", "99999999999999999999"], source);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = sdl(&["add", "server", "-", "cert \"a.pem\";"], source);
+    assert_eq!(
+        stdout(&output),
+        source.replace("tls;\n", "tls;\n  cert \"a.pem\";\n")
+    );
+
+    let output = sdl(&["delete", "server/tls", "-"], source);
+    assert_eq!(stdout(&output), source.replace("  tls;\n", ""));
+
+    let output = sdl(&["delete", "server/missing", "-"], source);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn dry_run() {
+    let path = file("dry_run.sdl", "a 1;\nb 2;\nc 3;\nd 4;\ne 5;\n");
+    let path = path.to_str().unwrap();
+
+    let output = sdl(&["set", "--dry-run", "e", path, "true"], "");
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "--- {0}\n+++ {0}\n@@ -2,4 +2,4 @@\n b 2;\n c 3;\n d 4;\n-e 5;\n+e true;\n",
+            path
+        )
+    );
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        "a 1;\nb 2;\nc 3;\nd 4;\ne 5;\n"
+    );
+}
+
+#[test]
+fn usage() {
+    assert_eq!(sdl(&[], "").status.code(), Some(2));
+    assert_eq!(sdl(&["frobnicate"], "").status.code(), Some(2));
+    assert_eq!(sdl(&["check", "--strict", "-"], "").status.code(), Some(2));
+}
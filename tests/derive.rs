@@ -0,0 +1,155 @@
+use rust_sdl::convert::{self, ConvertError};
+use rust_sdl::parser::{Parser, Tag, Value};
+use rust_sdl::writer::write;
+use rust_sdl::{FromSdl, ToSdl};
+use std::error::Error;
+
+#[derive(Debug, PartialEq, FromSdl, ToSdl)]
+struct Package {
+    #[sdl(value)]
+    name: String,
+    #[sdl(value)]
+    version: Option<String>,
+    #[sdl(attr)]
+    private: Option<bool>,
+    #[sdl(child)]
+    server: Server,
+    #[sdl(children = "dependency")]
+    dependencies: Vec<Dependency>,
+    author: Option<String>,
+}
+
+#[derive(Debug, PartialEq, FromSdl, ToSdl)]
+struct Server {
+    #[sdl(attr)]
+    port: i32,
+    #[sdl(attr = "max-connections")]
+    max_connections: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, FromSdl, ToSdl)]
+struct Dependency {
+    #[sdl(value)]
+    name: String,
+    #[sdl(attr)]
+    optional: Option<bool>,
+}
+
+#[derive(Debug, FromSdl, ToSdl)]
+struct Loose {
+    #[sdl(value)]
+    r#type: String,
+    #[sdl(rest)]
+    rest: Tag,
+}
+
+fn parse(source: &str) -> Vec<Tag> {
+    Parser::from_str(source).parse().unwrap()
+}
+
+fn package(source: &str) -> Result<Package, ConvertError> {
+    convert::from_tag(parse(source).remove(0))
+}
+
+#[test]
+fn reads_fields() {
+    let package = package(
+        r#"package "rust-sdl" private=true {
+            author "jlkiri";
+            server port=8080;
+            dependency "unicode-xid";
+            dependency "syn" optional=true;
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        package,
+        Package {
+            name: String::from("rust-sdl"),
+            version: None,
+            private: Some(true),
+            server: Server {
+                port: 8080,
+                max_connections: None,
+            },
+            dependencies: vec![
+                Dependency {
+                    name: String::from("unicode-xid"),
+                    optional: None,
+                },
+                Dependency {
+                    name: String::from("syn"),
+                    optional: Some(true),
+                },
+            ],
+            author: Some(String::from("jlkiri")),
+        }
+    );
+}
+
+#[test]
+fn round_trips() {
+    let source = "package \"rust-sdl\" \"0.1.0\" {\n  server max-connections=100 port=8080;\n  dependency \"syn\" optional=false;\n}\n";
+    let package = package(source).unwrap();
+
+    assert_eq!(write(&[package.to_tag("package")]).unwrap(), source);
+}
+
+#[test]
+fn reports_lines() {
+    let error = package("package \"a\" {\n  server port=\"80\";\n}").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "attribute `port`: expected integer, found string at line 2"
+    );
+    assert_eq!(
+        error.source().unwrap().to_string(),
+        "expected integer, found string"
+    );
+
+    let error = package("package \"a\";").unwrap_err();
+    assert_eq!(error.to_string(), "missing child `server` at line 1");
+
+    let error = package("package 1 { server port=1; }").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "value `name`: expected string, found integer at line 1"
+    );
+
+    let error = package("package \"a\" {\n  server port=1;\n  author;\n}").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "expected a single string value in `author` at line 3"
+    );
+}
+
+#[test]
+fn keeps_rest() {
+    let loose: Loose = convert::from_tag(parse("loose \"x\" 1 k=true { a; }").remove(0)).unwrap();
+
+    assert_eq!(loose.r#type, "x");
+    assert_eq!(loose.rest.values(), &[Value::from(1)]);
+    assert_eq!(loose.rest.attribute("k"), Some(&Value::Boolean(true)));
+    assert_eq!(
+        write(&[loose.to_tag("loose")]).unwrap(),
+        "loose \"x\" 1 k=true {\n  a;\n}\n"
+    );
+}
+
+#[test]
+fn documents() {
+    #[derive(FromSdl, ToSdl)]
+    struct Config {
+        author: String,
+        #[sdl(children = "package")]
+        packages: Vec<Dependency>,
+    }
+
+    let source = "author \"jlkiri\";\npackage \"a\";\npackage \"b\";\n";
+    let config: Config = convert::from_document(parse(source)).unwrap();
+
+    assert_eq!(config.author, "jlkiri");
+    assert_eq!(config.packages.len(), 2);
+    assert_eq!(write(&convert::to_document(&config)).unwrap(), source);
+}
@@ -0,0 +1,112 @@
+// Checks that scanning and parsing terminate without panicking on any input.
+// The files in `tests/corpus` double as the seed corpus of the fuzz targets
+// in `fuzz/`; inputs found by fuzzing belong there too.
+
+use rust_sdl::parser::Parser;
+use rust_sdl::scanner::{Scanner, Token};
+use std::fs;
+use std::io::BufReader;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+// Every token but the last consumes at least one character, so a scanner
+// that makes progress returns at most this many tokens.
+fn token_bound(source: &str) -> usize {
+    source.chars().count() + 1
+}
+
+fn check(source: &str) {
+    let bound = token_bound(source);
+    let mut scanner = Scanner::new(source);
+    let tokens: Vec<Token> = scanner.by_ref().take(bound).collect();
+
+    assert_eq!(scanner.next(), None, "scanner did not stop on {:?}", source);
+
+    let mut previous_end = 0;
+    let mut previous_line = 1;
+    for token in &tokens {
+        let (start, end, line) = token.position();
+        assert!(start <= end, "{:?} in {:?}", token, source);
+        assert!(start >= previous_end, "{:?} in {:?}", token, source);
+        assert!(end <= source.len(), "{:?} in {:?}", token, source);
+        assert!(line >= previous_line, "{:?} in {:?}", token, source);
+        previous_end = end;
+        previous_line = line;
+    }
+
+    let streamed: Vec<Token> = Scanner::from_reader(BufReader::with_capacity(1, source.as_bytes()))
+        .take(bound)
+        .collect();
+    assert_eq!(streamed, tokens, "reader differs on {:?}", source);
+
+    let _ = Parser::from_str(source).parse();
+}
+
+// A hang happens inside a single call to the scanner, so it is detected by
+// running the checks on another thread.
+fn terminates(name: &'static str, checks: impl FnOnce() + Send + 'static) {
+    let (done, finished) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        checks();
+        let _ = done.send(());
+    });
+
+    match finished.recv_timeout(Duration::from_secs(60)) {
+        Ok(()) => handle.join().unwrap(),
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            if let Err(panic) = handle.join() {
+                std::panic::resume_unwind(panic);
+            }
+        }
+        Err(mpsc::RecvTimeoutError::Timeout) => panic!("{} did not terminate", name),
+    }
+}
+
+#[test]
+fn corpus() {
+    terminates("corpus", check_corpus);
+}
+
+#[test]
+fn generated() {
+    terminates("generated inputs", check_generated);
+}
+
+fn check_corpus() {
+    let mut entries: Vec<_> = fs::read_dir("tests/corpus")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+
+    assert!(!entries.is_empty());
+
+    for path in entries {
+        check(&fs::read_to_string(&path).unwrap());
+    }
+}
+
+fn check_generated() {
+    const ALPHABET: &[char] = &[
+        ' ', '\n', '\r', '\t', '-', '/', '#', '\\', ';', '=', '{', '}', '"', '.', 'e', 'E', '+',
+        '0', '1', '9', 'a', 't', 'r', 'u', '_', ':', '$', 'é', '語', '✓',
+    ];
+
+    // xorshift64, so that failures are reproducible.
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..5_000 {
+        let len = (next() % 24) as usize;
+        let source: String = (0..len)
+            .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize])
+            .collect();
+        check(&source);
+    }
+}